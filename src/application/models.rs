@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{Cert, IsQueryArgs, Model, Organization, Provider, ThemeData};
+use crate::{Cert, IsQueryArgs, Model, Organization, Provider, Secret, ThemeData};
 
 #[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -42,7 +42,7 @@ pub struct Application {
     is_shared: bool,
 
     client_id: String,
-    client_secret: String,
+    client_secret: Secret,
     redirect_uris: Vec<String>,
     token_format: String,
     token_signing_method: String,
@@ -175,3 +175,19 @@ pub struct ApplicationQueryArgs {
 }
 
 impl IsQueryArgs for ApplicationQueryArgs {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_application_secrets() {
+        let application: Application =
+            serde_json::from_str(r#"{"owner": "admin", "name": "app", "clientSecret": "example_client_secret"}"#)
+                .unwrap();
+        assert!(!format!("{application:?}").contains("example_client_secret"));
+        let value = serde_json::to_value(&application).unwrap();
+        assert_eq!("example_client_secret", value["clientSecret"]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{fmt::Display, str::FromStr};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm,
+    errors::{ErrorKind, Result},
+};
+use p521::ecdsa::{Signature, VerifyingKey, signature::Verifier};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::ConfigError;
+
+/// An algorithm JWTs may be signed with, named like the
+/// `token_signing_method` of a Casdoor application, see
+/// [`SdkBuilder::jwt_algorithms`](crate::SdkBuilder::jwt_algorithms).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum JwtAlgorithm {
+    HS256,
+    HS384,
+    HS512,
+    RS256,
+    RS384,
+    RS512,
+    PS256,
+    PS384,
+    PS512,
+    ES256,
+    ES384,
+    /// ECDSA on the P-521 curve with SHA-512.
+    ES512,
+}
+
+impl JwtAlgorithm {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::HS256 => "HS256",
+            Self::HS384 => "HS384",
+            Self::HS512 => "HS512",
+            Self::RS256 => "RS256",
+            Self::RS384 => "RS384",
+            Self::RS512 => "RS512",
+            Self::PS256 => "PS256",
+            Self::PS384 => "PS384",
+            Self::PS512 => "PS512",
+            Self::ES256 => "ES256",
+            Self::ES384 => "ES384",
+            Self::ES512 => "ES512",
+        }
+    }
+
+    /// Whether JWTs signed with the algorithm are verified with the client
+    /// secret.
+    pub const fn is_hmac(self) -> bool {
+        matches!(self, Self::HS256 | Self::HS384 | Self::HS512)
+    }
+
+    /// The same algorithm of jsonwebtoken, which cannot verify ES512.
+    pub(crate) const fn to_jsonwebtoken(self) -> Option<Algorithm> {
+        Some(match self {
+            Self::HS256 => Algorithm::HS256,
+            Self::HS384 => Algorithm::HS384,
+            Self::HS512 => Algorithm::HS512,
+            Self::RS256 => Algorithm::RS256,
+            Self::RS384 => Algorithm::RS384,
+            Self::RS512 => Algorithm::RS512,
+            Self::PS256 => Algorithm::PS256,
+            Self::PS384 => Algorithm::PS384,
+            Self::PS512 => Algorithm::PS512,
+            Self::ES256 => Algorithm::ES256,
+            Self::ES384 => Algorithm::ES384,
+            Self::ES512 => return None,
+        })
+    }
+}
+
+impl FromStr for JwtAlgorithm {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "HS256" => Self::HS256,
+            "HS384" => Self::HS384,
+            "HS512" => Self::HS512,
+            "RS256" => Self::RS256,
+            "RS384" => Self::RS384,
+            "RS512" => Self::RS512,
+            "PS256" => Self::PS256,
+            "PS384" => Self::PS384,
+            "PS512" => Self::PS512,
+            "ES256" => Self::ES256,
+            "ES384" => Self::ES384,
+            "ES512" => Self::ES512,
+            _ => return Err(ConfigError::UnsupportedJwtAlgorithm(s.to_owned())),
+        })
+    }
+}
+
+impl Display for JwtAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The `alg` and `kid` of a JWT. Unlike [`jsonwebtoken::decode_header`],
+/// this knows ES512; other unknown algorithms are an
+/// [`ErrorKind::InvalidAlgorithm`].
+pub(crate) fn decode_header(token: &str) -> Result<(JwtAlgorithm, Option<String>)> {
+    #[derive(Deserialize)]
+    struct Header {
+        alg: String,
+        #[serde(default)]
+        kid: Option<String>,
+    }
+
+    let header = token.split('.').next().unwrap_or_default();
+    let header: Header = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header)?)?;
+    let algorithm = header.alg.parse().map_err(|_| ErrorKind::InvalidAlgorithm)?;
+    Ok((algorithm, header.kid))
+}
+
+/// Verifies an ES512 token with the P-521 key and returns its claims,
+/// checking `exp` and `nbf` like [`jsonwebtoken::decode`] does.
+pub(crate) fn decode_es512(token: &str, key: &VerifyingKey, leeway: u64) -> Result<Value> {
+    let (message, signature) = token.rsplit_once('.').ok_or(ErrorKind::InvalidToken)?;
+    let (_, payload) = message.split_once('.').ok_or(ErrorKind::InvalidToken)?;
+    let signature =
+        Signature::from_slice(&URL_SAFE_NO_PAD.decode(signature)?).map_err(|_| ErrorKind::InvalidSignature)?;
+    key.verify(message.as_bytes(), &signature)
+        .map_err(|_| ErrorKind::InvalidSignature)?;
+
+    let claims: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)?;
+    let now = jsonwebtoken::get_current_timestamp();
+    let exp = claims
+        .get("exp")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| ErrorKind::MissingRequiredClaim("exp".to_owned()))?;
+    if exp < now.saturating_sub(leeway) {
+        return Err(ErrorKind::ExpiredSignature.into());
+    }
+    if claims
+        .get("nbf")
+        .and_then(Value::as_u64)
+        .is_some_and(|nbf| nbf > now + leeway)
+    {
+        return Err(ErrorKind::ImmatureSignature.into());
+    }
+    Ok(claims)
+}
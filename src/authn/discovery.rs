@@ -0,0 +1,234 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Method, NO_BODY, RequestOptions, Sdk, SdkError, SdkResult};
+
+/// The OpenID Connect discovery document of Casdoor, see
+/// [`AuthSdk::discover`](super::AuthSdk::discover).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+    #[serde(default)]
+    pub introspection_endpoint: Option<String>,
+    #[serde(default)]
+    pub end_session_endpoint: Option<String>,
+    #[serde(default)]
+    pub response_types_supported: Vec<String>,
+    #[serde(default)]
+    pub response_modes_supported: Vec<String>,
+    #[serde(default)]
+    pub grant_types_supported: Vec<String>,
+    #[serde(default)]
+    pub subject_types_supported: Vec<String>,
+    #[serde(default)]
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+    #[serde(default)]
+    pub claims_supported: Vec<String>,
+}
+
+/// Controls how [`AuthSdk`](super::AuthSdk) uses the discovery document, see
+/// [`SdkBuilder::discovery_policy`](crate::SdkBuilder::discovery_policy).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveryPolicy {
+    /// Path of the document, `/.well-known/openid-configuration` by default.
+    pub path: String,
+    /// How long the document is cached.
+    pub ttl: Duration,
+    /// Take the authorize, token, userinfo, JWKS and end-session URLs from
+    /// the document instead of the fixed Casdoor paths. Off by default.
+    pub derive_urls: bool,
+}
+
+impl Default for DiscoveryPolicy {
+    fn default() -> Self {
+        Self {
+            path: "/.well-known/openid-configuration".to_owned(),
+            ttl: Duration::from_secs(3600),
+            derive_urls: false,
+        }
+    }
+}
+
+/// The last fetched discovery document of a [`Sdk`].
+#[derive(Debug)]
+pub(crate) struct DiscoveryCache {
+    policy: DiscoveryPolicy,
+    cached: Mutex<Option<(Arc<ProviderMetadata>, Instant)>>,
+    /// Held while fetching, so concurrent calls fetch once.
+    fetching: tokio::sync::Mutex<()>,
+}
+
+impl DiscoveryCache {
+    pub(crate) fn new(policy: DiscoveryPolicy) -> Self {
+        Self {
+            policy,
+            cached: Mutex::default(),
+            fetching: tokio::sync::Mutex::default(),
+        }
+    }
+
+    pub(crate) const fn policy(&self) -> &DiscoveryPolicy {
+        &self.policy
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<(Arc<ProviderMetadata>, Instant)>> {
+        self.cached.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The cached document, also after its TTL, if URLs are derived from
+    /// it.
+    pub(crate) fn derived(&self) -> Option<Arc<ProviderMetadata>> {
+        if !self.policy.derive_urls {
+            return None;
+        }
+        self.lock().as_ref().map(|(metadata, _)| metadata.clone())
+    }
+
+    fn fresh(&self) -> Option<Arc<ProviderMetadata>> {
+        self.lock()
+            .as_ref()
+            .filter(|(_, fetched_at)| fetched_at.elapsed() < self.policy.ttl)
+            .map(|(metadata, _)| metadata.clone())
+    }
+
+    /// The cached document, fetched again after its TTL.
+    pub(crate) async fn get(&self, sdk: &Sdk) -> SdkResult<Arc<ProviderMetadata>> {
+        if let Some(metadata) = self.fresh() {
+            return Ok(metadata);
+        }
+        let _fetching = self.fetching.lock().await;
+        if let Some(metadata) = self.fresh() {
+            return Ok(metadata);
+        }
+        let res = sdk
+            .request_raw(
+                Method::GET,
+                &self.policy.path,
+                NO_BODY,
+                RequestOptions::new().operation("get_provider_metadata"),
+            )
+            .await?;
+        if !res.status.is_success() {
+            return Err(SdkError::unexpected_response(&res));
+        }
+        let metadata: Arc<ProviderMetadata> =
+            Arc::new(serde_json::from_slice(&res.body).map_err(|_| SdkError::unexpected_response(&res))?);
+        *self.lock() = Some((metadata.clone(), Instant::now()));
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{MockTransport, header::AUTHORIZATION, test_support::mock_sdk_with};
+
+    fn mock_sdk(policy: DiscoveryPolicy) -> (Sdk, MockTransport) {
+        mock_sdk_with(|builder| builder.discovery_policy(policy))
+    }
+
+    fn document() -> serde_json::Value {
+        json!({
+            "issuer": "https://login.example.com",
+            "authorization_endpoint": "https://login.example.com/login/oauth/authorize",
+            "token_endpoint": "https://login.example.com/api/login/oauth/access_token",
+            "userinfo_endpoint": "https://login.example.com/api/userinfo",
+            "jwks_uri": "https://login.example.com/.well-known/jwks",
+            "end_session_endpoint": "https://login.example.com/api/logout",
+            "response_types_supported": ["code", "token", "id_token"],
+            "id_token_signing_alg_values_supported": ["RS256"],
+            "scopes_supported": ["openid", "email", "profile"],
+            "claims_supported": ["iss", "sub", "name"],
+            "request_parameter_supported": true,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_discover() {
+        let (sdk, transport) = mock_sdk(DiscoveryPolicy::default());
+        let authn = sdk.authn();
+        transport.push_json(&document());
+        let metadata = authn.discover().await.unwrap();
+        assert_eq!("https://login.example.com", metadata.issuer);
+        assert_eq!(vec!["RS256"], metadata.id_token_signing_alg_values_supported);
+        assert_eq!(metadata, authn.discover().await.unwrap());
+        assert_eq!(1, transport.requests().len());
+        assert_eq!(
+            "http://casdoor.test/.well-known/openid-configuration",
+            transport.last_request().unwrap().url
+        );
+
+        // URLs are not derived by default.
+        assert!(
+            authn
+                .get_signin_url("https://app/".to_owned())
+                .starts_with("http://casdoor.test/login/")
+        );
+        transport.push_json(&json!({"status": "error", "msg": "Access token doesn't exist"}));
+        assert!(authn.get_userinfo("token").await.is_err());
+        assert_eq!(
+            "http://casdoor.test/api/userinfo",
+            transport.last_request().unwrap().url
+        );
+
+        let (sdk, transport) = mock_sdk(DiscoveryPolicy {
+            ttl: Duration::ZERO,
+            ..DiscoveryPolicy::default()
+        });
+        let authn = sdk.authn();
+        transport.push_json(&document()).push_json(&document());
+        authn.discover().await.unwrap();
+        authn.discover().await.unwrap();
+        assert_eq!(0, transport.pending());
+    }
+
+    #[tokio::test]
+    async fn test_derive_urls() {
+        let (sdk, transport) = mock_sdk(DiscoveryPolicy {
+            derive_urls: true,
+            ..DiscoveryPolicy::default()
+        });
+        let authn = sdk.authn();
+        transport
+            .push_json(&document())
+            .push_json(&json!({"access_token": "token", "token_type": "bearer"}));
+        authn.get_oauth_token("code".to_owned()).await.unwrap();
+        assert_eq!(
+            "https://login.example.com/api/login/oauth/access_token",
+            transport.last_request().unwrap().url
+        );
+
+        assert!(
+            authn
+                .get_signin_url("https://app/".to_owned())
+                .starts_with("https://login.example.com/login/oauth/authorize?client_id=cid&")
+        );
+        assert_eq!(
+            "https://login.example.com/api/logout?id_token_hint=id.token&post_logout_redirect_uri=https%3A%2F%2Fapp%2F",
+            authn.get_signout_url("id.token", Some("https://app/"), None)
+        );
+
+        transport.push_json(&json!({"sub": "1", "preferred_username": "alice", "email_verified": true}));
+        let userinfo = authn.get_userinfo("token").await.unwrap();
+        assert_eq!("alice", userinfo.preferred_username);
+        assert!(userinfo.email_verified);
+        let req = transport.last_request().unwrap();
+        assert_eq!("https://login.example.com/api/userinfo", req.url);
+        assert_eq!("Bearer token", req.headers[AUTHORIZATION]);
+        assert_eq!(3, transport.requests().len());
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    DecodingKey,
+    jwk::{AlgorithmParameters, EllipticCurve, Jwk, PublicKeyUse},
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    HttpRequest, JwtAlgorithm, KeyType, Method, NO_BODY, ParsedCertificate, RequestOptions, Sdk, SdkError, SdkResult,
+    VerificationKey, header::HeaderMap,
+};
+
+/// Controls the JWKS key store of [`AuthSdk`](super::AuthSdk), see
+/// [`SdkBuilder::jwks`](crate::SdkBuilder::jwks).
+///
+/// [`AuthSdk::verify_jwt_token`](super::AuthSdk::verify_jwt_token) fetches
+/// the key set when it is older than `max_age`, or when a token has a `kid`
+/// that is not in it. Fetches are at least `min_refresh_interval` apart, so
+/// tokens with made-up `kid`s cannot flood Casdoor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwksPolicy {
+    /// Path of the key set, `/.well-known/jwks` by default.
+    pub path: String,
+    pub max_age: Duration,
+    pub min_refresh_interval: Duration,
+}
+
+impl Default for JwksPolicy {
+    fn default() -> Self {
+        Self {
+            path: "/.well-known/jwks".to_owned(),
+            max_age: Duration::from_secs(3600),
+            min_refresh_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The last fetched key set of a [`Sdk`].
+#[derive(Debug)]
+pub(crate) struct JwksStore {
+    policy: JwksPolicy,
+    keys: Mutex<JwksKeys>,
+    /// Held while fetching, so concurrent verifications fetch once.
+    fetching: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug, Default)]
+struct JwksKeys {
+    keys: Vec<(Option<String>, Arc<ParsedCertificate>)>,
+    fetched_at: Option<Instant>,
+    attempted_at: Option<Instant>,
+}
+
+impl JwksStore {
+    pub(crate) fn new(policy: JwksPolicy) -> Self {
+        Self {
+            policy,
+            keys: Mutex::default(),
+            fetching: tokio::sync::Mutex::default(),
+        }
+    }
+
+    pub(crate) const fn policy(&self) -> &JwksPolicy {
+        &self.policy
+    }
+
+    fn lock(&self) -> MutexGuard<'_, JwksKeys> {
+        self.keys.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The keys with the `kid`, or all keys for a token without one.
+    pub(crate) fn keys_for(&self, kid: Option<&str>) -> Vec<Arc<ParsedCertificate>> {
+        self.lock()
+            .keys
+            .iter()
+            .filter(|(key_id, _)| kid.is_none() || key_id.as_deref() == kid)
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    /// Whether the key set is stale or lacks the `kid`, and the last fetch
+    /// is at least the minimum refresh interval ago.
+    pub(crate) fn needs_refresh(&self, kid: Option<&str>) -> bool {
+        let keys = self.lock();
+        let now = Instant::now();
+        if keys
+            .attempted_at
+            .is_some_and(|at| now.duration_since(at) < self.policy.min_refresh_interval)
+        {
+            return false;
+        }
+        let stale = keys
+            .fetched_at
+            .is_none_or(|at| now.duration_since(at) >= self.policy.max_age);
+        stale || kid.is_some_and(|kid| !keys.keys.iter().any(|(key_id, _)| key_id.as_deref() == Some(kid)))
+    }
+
+    /// Fetches the key set if it [needs a refresh](Self::needs_refresh).
+    pub(crate) async fn refresh(&self, sdk: &Sdk, kid: Option<&str>) -> SdkResult<()> {
+        let _fetching = self.fetching.lock().await;
+        if !self.needs_refresh(kid) {
+            return Ok(());
+        }
+        self.fetch(sdk).await
+    }
+
+    /// Fetches the key set now.
+    pub(crate) async fn refresh_now(&self, sdk: &Sdk) -> SdkResult<()> {
+        let _fetching = self.fetching.lock().await;
+        self.fetch(sdk).await
+    }
+
+    async fn fetch(&self, sdk: &Sdk) -> SdkResult<()> {
+        self.lock().attempted_at = Some(Instant::now());
+        let options = RequestOptions::new().operation("get_jwks");
+        let res = if sdk.discovery_policy().derive_urls {
+            let req = HttpRequest {
+                method: Method::GET,
+                url: sdk.discovery().get(sdk).await?.jwks_uri.clone(),
+                headers: HeaderMap::new(),
+                body: Vec::new(),
+            };
+            sdk.send_raw(req, &sdk.endpoints(), options).await?
+        } else {
+            sdk.request_raw(Method::GET, &self.policy.path, NO_BODY, options)
+                .await?
+        };
+        if !res.status.is_success() {
+            return Err(SdkError::unexpected_response(&res));
+        }
+        let set: JwkValues = serde_json::from_slice(&res.body).map_err(|_| SdkError::unexpected_response(&res))?;
+        let mut keys = self.lock();
+        keys.keys = set.keys.into_iter().filter_map(parse_jwk).collect();
+        keys.fetched_at = Some(Instant::now());
+        Ok(())
+    }
+}
+
+/// A JWK set whose keys are parsed one by one, as a [`Jwk`] cannot hold
+/// the `alg` ES512.
+#[derive(Deserialize)]
+struct JwkValues {
+    keys: Vec<Value>,
+}
+
+/// The `kid` and verification key of a JWK. Keys of unsupported types or
+/// algorithms are skipped.
+fn parse_jwk(mut value: Value) -> Option<(Option<String>, Arc<ParsedCertificate>)> {
+    // A JWK may pin its algorithm, e.g. PS256 instead of RS256.
+    let algorithm = match value.as_object_mut()?.remove("alg") {
+        Some(Value::String(alg)) => Some(alg.parse::<JwtAlgorithm>().ok()?),
+        Some(_) => return None,
+        None => None,
+    };
+    let jwk: Jwk = serde_json::from_value(value).ok()?;
+    let key_type = match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => KeyType::Rsa,
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P256 => KeyType::EcP256,
+            EllipticCurve::P384 => KeyType::EcP384,
+            EllipticCurve::P521 => KeyType::EcP521,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    if jwk.common.public_key_use == Some(PublicKeyUse::Encryption) {
+        return None;
+    }
+    let key = match &jwk.algorithm {
+        AlgorithmParameters::EllipticCurve(params) if key_type == KeyType::EcP521 => {
+            let mut point = vec![0x04];
+            point.extend(URL_SAFE_NO_PAD.decode(&params.x).ok()?);
+            point.extend(URL_SAFE_NO_PAD.decode(&params.y).ok()?);
+            VerificationKey::P521(p521::ecdsa::VerifyingKey::from_sec1_bytes(&point).ok()?)
+        }
+        _ => VerificationKey::Jwt(DecodingKey::from_jwk(&jwk).ok()?),
+    };
+    let key = ParsedCertificate {
+        info: None,
+        key_type,
+        algorithm,
+        key,
+    };
+    Some((jwk.common.key_id.clone(), Arc::new(key)))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use serde_json::json;
+    use x509_parser::{prelude::FromDer, public_key::PublicKey};
+
+    use super::*;
+    use crate::{
+        MockTransport, SdkInnerError, StatusCode,
+        test_support::{
+            EC521_CERT, EC521_KEY, RSA1_CERT, RSA1_KEY, RSA2_CERT, RSA2_KEY, claims, mock_sdk_for, sign, sign_es512,
+        },
+    };
+
+    /// The RSA JWK of the certificate, like Casdoor publishes it.
+    fn jwk(kid: &str, certificate: &str) -> serde_json::Value {
+        let pem = pem::parse(certificate).unwrap();
+        let (_, certificate) = x509_parser::certificate::X509Certificate::from_der(pem.contents()).unwrap();
+        let Ok(PublicKey::RSA(key)) = certificate.public_key().parsed() else {
+            panic!("not an RSA certificate");
+        };
+        let trim = |bytes: &[u8]| URL_SAFE_NO_PAD.encode(&bytes[bytes.iter().take_while(|b| **b == 0).count()..]);
+        json!({"kty": "RSA", "use": "sig", "alg": "RS256", "kid": kid, "n": trim(key.modulus), "e": trim(key.exponent)})
+    }
+
+    fn mock_sdk() -> (Sdk, MockTransport) {
+        mock_sdk_for(RSA1_CERT, |builder| builder.jwks(JwksPolicy::default()))
+    }
+
+    #[tokio::test]
+    async fn test_jwks() {
+        let (sdk, transport) = mock_sdk();
+        let authn = sdk.authn();
+        let rotated = sign(RSA2_KEY, Some("cert-2"), &claims());
+        assert!(authn.parse_jwt_token(&rotated).is_err());
+
+        transport.push_json(&json!({"keys": [jwk("cert-2", RSA2_CERT), {"kty": "oct", "k": "c2VjcmV0"}]}));
+        assert_eq!("alice", authn.verify_jwt_token(&rotated).await.unwrap().user.name);
+        assert_eq!(
+            "http://casdoor.test/.well-known/jwks",
+            transport.last_request().unwrap().url
+        );
+        assert_eq!("alice", authn.parse_jwt_token(&rotated).unwrap().user.name);
+
+        // Unknown kids fall back to the certificate without refetching
+        // within the minimum refresh interval.
+        assert!(
+            authn
+                .verify_jwt_token(&sign(RSA1_KEY, Some("cert-1"), &claims()))
+                .await
+                .is_ok()
+        );
+        assert!(authn.verify_jwt_token(&sign(RSA1_KEY, None, &claims())).await.is_ok());
+        assert!(
+            authn
+                .verify_jwt_token(&sign(RSA1_KEY, Some("cert-3"), &claims()))
+                .await
+                .is_ok()
+        );
+        assert_eq!(1, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_jwks_es512() {
+        let (sdk, transport) = mock_sdk();
+        let authn = sdk.authn();
+        let pem = pem::parse(EC521_CERT).unwrap();
+        let (_, certificate) = x509_parser::certificate::X509Certificate::from_der(pem.contents()).unwrap();
+        let Ok(PublicKey::EC(point)) = certificate.public_key().parsed() else {
+            panic!("not an EC certificate");
+        };
+        let (x, y) = point.data()[1..].split_at(66);
+        let es512 = json!({
+            "kty": "EC", "crv": "P-521", "use": "sig", "alg": "ES512", "kid": "cert-521",
+            "x": URL_SAFE_NO_PAD.encode(x), "y": URL_SAFE_NO_PAD.encode(y),
+        });
+        transport.push_json(&json!({"keys": [es512, jwk("cert-2", RSA2_CERT)]}));
+
+        let token = sign_es512(EC521_KEY, Some("cert-521"), &claims());
+        assert_eq!("alice", authn.verify_jwt_token(&token).await.unwrap().user.name);
+        assert!(
+            authn
+                .parse_jwt_token(&sign(RSA2_KEY, Some("cert-2"), &claims()))
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jwks_unavailable() {
+        let (sdk, transport) = mock_sdk();
+        let authn = sdk.authn();
+        transport.push_error(SdkError::new(
+            StatusCode::BAD_GATEWAY,
+            SdkInnerError::TransportError("connection refused".to_owned()),
+        ));
+        let err = authn
+            .verify_jwt_token(&sign(RSA2_KEY, Some("cert-2"), &claims()))
+            .await
+            .unwrap_err();
+        assert!(err.is_transport());
+        assert!(
+            authn
+                .verify_jwt_token(&sign(RSA1_KEY, Some("cert-1"), &claims()))
+                .await
+                .is_ok()
+        );
+
+        transport.push_response(crate::HttpResponse::new(StatusCode::NOT_FOUND, "404 page not found"));
+        assert!(authn.refresh_jwks().await.unwrap_err().is_decode());
+    }
+}
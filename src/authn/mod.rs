@@ -1,87 +1,386 @@
+mod algorithm;
+mod discovery;
+mod jwks;
 mod models;
-use jsonwebtoken::{Algorithm, DecodingKey, TokenData, Validation};
+mod validation;
+
+use std::sync::Arc;
+
+pub use algorithm::JwtAlgorithm;
+pub(crate) use discovery::DiscoveryCache;
+pub use discovery::{DiscoveryPolicy, ProviderMetadata};
+use jsonwebtoken::{DecodingKey, Validation, errors::ErrorKind};
+pub use jwks::JwksPolicy;
+pub(crate) use jwks::JwksStore;
 pub use models::*;
 pub use oauth2::{AccessToken, RefreshToken, Scope, TokenResponse, TokenType, basic::BasicTokenType};
-use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, TokenUrl, basic::BasicClient, reqwest::async_http_client,
+use oauth2::{AuthUrl, AuthorizationCode, ClientId, ClientSecret, TokenUrl, basic::BasicClient};
+use serde_json::Value;
+pub use validation::{TokenValidationError, TokenValidationPolicy};
+
+use crate::{
+    ConfigError, HttpRequest, Method, NO_BODY, Outcome, ParsedCertificate, QueryArgs, QueryResult, RequestOptions,
+    RequestTrace, Sdk, SdkError, SdkResult, StatusCode, VerificationKey,
+    header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue},
+    record_request,
 };
+/// The algorithms [`AuthSdk::parse_jwt_token`] accepts by default: all
+/// RSA and EC algorithms. HMAC with the client secret has to be allowed
+/// explicitly with
+/// [`SdkBuilder::jwt_algorithms`](crate::SdkBuilder::jwt_algorithms).
+pub const DEFAULT_JWT_ALGORITHMS: [JwtAlgorithm; 9] = [
+    JwtAlgorithm::RS256,
+    JwtAlgorithm::RS384,
+    JwtAlgorithm::RS512,
+    JwtAlgorithm::PS256,
+    JwtAlgorithm::PS384,
+    JwtAlgorithm::PS512,
+    JwtAlgorithm::ES256,
+    JwtAlgorithm::ES384,
+    JwtAlgorithm::ES512,
+];
 
-use crate::{Method, NO_BODY, QueryArgs, QueryResult, Sdk, SdkResult};
 impl Sdk {
     pub fn authn(&self) -> AuthSdk {
-        AuthSdk { sdk: self.clone() }
+        AuthSdk {
+            sdk: self.clone(),
+            validation_policy: Arc::default(),
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct AuthSdk {
     sdk: Sdk,
+    validation_policy: Arc<TokenValidationPolicy>,
 }
 
 impl AuthSdk {
+    /// Returns a handle that validates JWTs with the policy, see
+    /// [`AuthSdk::parse_jwt_token`].
+    pub fn with_validation_policy(&self, policy: TokenValidationPolicy) -> Self {
+        Self {
+            sdk: self.sdk.clone(),
+            validation_policy: Arc::new(policy),
+        }
+    }
+
+    pub fn validation_policy(&self) -> &TokenValidationPolicy {
+        &self.validation_policy
+    }
+
     fn client_id(&self) -> ClientId {
-        ClientId::new(self.sdk.client_id().clone())
+        ClientId::new(self.sdk.config().client_id().clone())
     }
     fn client_secret(&self) -> Option<ClientSecret> {
-        Some(ClientSecret::new(self.sdk.client_secret().clone()))
+        Some(ClientSecret::new(
+            self.sdk.config().client_secret().expose_secret().clone(),
+        ))
     }
     fn auth_url(&self, url_path: &str) -> Result<AuthUrl, oauth2::url::ParseError> {
-        AuthUrl::new(self.sdk.endpoint().clone() + url_path)
+        AuthUrl::new(self.sdk.config().browser_endpoint().clone() + url_path)
     }
     fn token_url(&self, url_path: &str) -> Result<Option<TokenUrl>, oauth2::url::ParseError> {
-        Ok(Some(TokenUrl::new(self.sdk.endpoint().clone() + url_path)?))
+        Ok(Some(TokenUrl::new(self.sdk.config().endpoint().clone() + url_path)?))
+    }
+
+    /// The authorize and token URLs, from the discovery document if
+    /// [derived](DiscoveryPolicy::derive_urls).
+    async fn oauth_urls(&self, token_path: &str) -> SdkResult<(AuthUrl, Option<TokenUrl>)> {
+        Ok(match self.derived_metadata().await? {
+            Some(metadata) => (
+                AuthUrl::new(metadata.authorization_endpoint.clone())?,
+                Some(TokenUrl::new(metadata.token_endpoint.clone())?),
+            ),
+            None => (
+                self.auth_url("/api/login/oauth/authorize")?,
+                self.token_url(token_path)?,
+            ),
+        })
+    }
+
+    /// The discovery document if URLs are
+    /// [derived](DiscoveryPolicy::derive_urls) from it.
+    async fn derived_metadata(&self) -> SdkResult<Option<Arc<ProviderMetadata>>> {
+        if !self.sdk.discovery_policy().derive_urls {
+            return Ok(None);
+        }
+        self.discover().await.map(Some)
+    }
+
+    /// Fetches the OpenID Connect discovery document, or returns the one
+    /// cached within the [TTL](DiscoveryPolicy::ttl).
+    pub async fn discover(&self) -> SdkResult<Arc<ProviderMetadata>> {
+        self.sdk.discovery().get(&self.sdk).await
+    }
+
+    /// Sends the OAuth request through the transport of the Sdk.
+    async fn oauth2_http_client(
+        &self,
+        operation: &'static str,
+        request: oauth2::HttpRequest,
+    ) -> SdkResult<oauth2::HttpResponse> {
+        let mut headers = HeaderMap::with_capacity(request.headers.len());
+        for (name, value) in &request.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_str().as_bytes())
+                    .map_err(|e| SdkError::new(StatusCode::BAD_REQUEST, e.to_string()))?,
+                HeaderValue::from_bytes(value.as_bytes())
+                    .map_err(|e| SdkError::new(StatusCode::BAD_REQUEST, e.to_string()))?,
+            );
+        }
+        let mut req = HttpRequest {
+            method: Method::from_bytes(request.method.as_str().as_bytes())
+                .map_err(|e| SdkError::new(StatusCode::BAD_REQUEST, e.to_string()))?,
+            url: request.url.into(),
+            headers,
+            body: request.body,
+        };
+        for middleware in self.sdk.middlewares() {
+            middleware.on_request(&mut req);
+        }
+        let trace = RequestTrace::new(&req, operation);
+        let (mut res, info) = self
+            .sdk
+            .execute(&req, &self.sdk.endpoints(), &RequestOptions::new(), &trace)
+            .await;
+        for middleware in self.sdk.middlewares() {
+            middleware.on_oauth_response(&req, &info, &mut res);
+        }
+        record_request(operation, Outcome::of(res.as_ref().map(|_| None)), info.elapsed);
+        trace.finish(res.as_ref().map(|_| None));
+        let res = res?;
+        let mut headers = oauth2::http::HeaderMap::with_capacity(res.headers.len());
+        for (name, value) in &res.headers {
+            if let (Ok(name), Ok(value)) = (
+                oauth2::http::HeaderName::from_bytes(name.as_str().as_bytes()),
+                oauth2::http::HeaderValue::from_bytes(value.as_bytes()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        Ok(oauth2::HttpResponse {
+            status_code: oauth2::http::StatusCode::from_u16(res.status.as_u16())
+                .map_err(|e| SdkError::new(StatusCode::BAD_GATEWAY, e.to_string()))?,
+            headers,
+            body: res.body,
+        })
     }
 
     /// Gets the pivotal and necessary secret to interact with the Casdoor
     /// server
     pub async fn get_oauth_token(&self, code: String) -> SdkResult<impl TokenResponse<BasicTokenType>> {
-        Ok(BasicClient::new(
-            self.client_id(),
-            self.client_secret(),
-            self.auth_url("/api/login/oauth/authorize")?,
-            self.token_url("/api/login/oauth/access_token")?,
+        let (auth_url, token_url) = self.oauth_urls("/api/login/oauth/access_token").await?;
+        Ok(
+            BasicClient::new(self.client_id(), self.client_secret(), auth_url, token_url)
+                .exchange_code(AuthorizationCode::new(code))
+                .request_async(|request| self.oauth2_http_client("get_oauth_token", request))
+                .await?,
         )
-        .exchange_code(AuthorizationCode::new(code))
-        .request_async(async_http_client)
-        .await?)
     }
 
     /// Refreshes the OAuth token
     pub async fn refresh_oauth_token(&self, refresh_token: String) -> SdkResult<impl TokenResponse<BasicTokenType>> {
-        Ok(BasicClient::new(
-            self.client_id(),
-            self.client_secret(),
-            self.auth_url("/api/login/oauth/authorize")?,
-            self.token_url("/api/login/oauth/refresh_token")?,
+        let (auth_url, token_url) = self.oauth_urls("/api/login/oauth/refresh_token").await?;
+        Ok(
+            BasicClient::new(self.client_id(), self.client_secret(), auth_url, token_url)
+                .exchange_refresh_token(&RefreshToken::new(refresh_token))
+                .request_async(|request| self.oauth2_http_client("refresh_oauth_token", request))
+                .await?,
         )
-        .exchange_refresh_token(&RefreshToken::new(refresh_token))
-        .request_async(async_http_client)
-        .await?)
     }
 
+    /// Verifies the token with the certificate of the config, or one replaced
+    /// within the [certificate
+    /// overlap](crate::SdkBuilder::certificate_overlap). With a
+    /// [JWKS](crate::SdkBuilder::jwks), the cached keys matching the `kid` of
+    /// the token are tried first; use [`AuthSdk::verify_jwt_token`] to fetch
+    /// them.
+    ///
+    /// The `alg` of the token must be one of the [allowed
+    /// algorithms](crate::SdkBuilder::jwt_algorithms), and only keys of the
+    /// matching type are tried: RSA keys for RS* and PS*, EC keys of the
+    /// curve for ES256, ES384 and ES512, and the client secret for HS*.
+    /// Unsigned tokens (`none`) are never accepted.
+    ///
+    /// The claims are checked with the [validation
+    /// policy](AuthSdk::with_validation_policy) of this handle.
     pub fn parse_jwt_token(&self, token: &str) -> SdkResult<Claims> {
-        let mut validation = Validation::new(Algorithm::RS256);
-        validation.set_audience(&[self.sdk.client_id()]);
+        self.parse_jwt_token_with(token, &self.validation_policy)
+    }
+
+    /// Like [`AuthSdk::parse_jwt_token`], but checks the claims with the
+    /// given policy. A token with a valid signature that the policy rejects
+    /// fails with a [`TokenValidationError`].
+    pub fn parse_jwt_token_with(&self, token: &str, policy: &TokenValidationPolicy) -> SdkResult<Claims> {
+        let (algorithm, kid) = algorithm::decode_header(token)?;
+        if !self.sdk.jwt_algorithms().contains(&algorithm) {
+            return Err(jsonwebtoken::errors::Error::from(ErrorKind::InvalidAlgorithm).into());
+        }
+        let config = self.sdk.config();
+        let leeway = policy.leeway.as_secs();
+        let decode = |key: &VerificationKey| -> jsonwebtoken::errors::Result<Value> {
+            match (key, algorithm.to_jsonwebtoken()) {
+                (VerificationKey::Jwt(key), Some(alg)) => {
+                    let mut validation = Validation::new(alg);
+                    validation.leeway = leeway;
+                    validation.validate_nbf = true;
+                    validation.validate_aud = false;
+                    Ok(jsonwebtoken::decode(token, key, &validation)?.claims)
+                }
+                (VerificationKey::P521(key), None) => algorithm::decode_es512(token, key, leeway),
+                _ => Err(ErrorKind::InvalidAlgorithm.into()),
+            }
+        };
 
-        let td: TokenData<Claims> = jsonwebtoken::decode(
-            token,
-            &DecodingKey::from_rsa_pem(self.sdk.certificate().as_bytes())?,
-            &validation,
-        )?;
-        Ok(td.claims)
+        let res = if algorithm.is_hmac() {
+            if config.client_secret().is_empty() {
+                return Err(ConfigError::MissingField("client_secret").into());
+            }
+            decode(&VerificationKey::Jwt(DecodingKey::from_secret(
+                config.client_secret().expose_secret().as_bytes(),
+            )))
+        } else {
+            let keys = self.verification_keys(kid.as_deref())?;
+            let mut res = Err(ErrorKind::InvalidAlgorithm.into());
+            for key in keys.iter().filter(|key| key.accepts(algorithm)) {
+                res = decode(&key.key);
+                if !matches!(res.as_ref().map_err(|e| e.kind()), Err(ErrorKind::InvalidSignature)) {
+                    break;
+                }
+            }
+            res
+        };
+        let raw = res.map_err(TokenValidationError::from_jwt_error)?;
+        let claims: Claims = serde_json::from_value(raw.clone()).map_err(jsonwebtoken::errors::Error::from)?;
+        policy.validate(&raw, &claims, config.client_id())?;
+        Ok(claims)
+    }
+
+    /// The cached JWKS keys matching the `kid`, followed by the
+    /// certificates of the config.
+    fn verification_keys(&self, kid: Option<&str>) -> SdkResult<Vec<Arc<ParsedCertificate>>> {
+        let mut keys = self.sdk.jwks().map(|jwks| jwks.keys_for(kid)).unwrap_or_default();
+        match self.sdk.verification_certificates() {
+            Ok(certificates) => keys.extend(certificates),
+            Err(e) if keys.is_empty() => return Err(e.into()),
+            Err(_) => {}
+        }
+        if keys.is_empty() {
+            return Err(ConfigError::MissingField("certificate").into());
+        }
+        Ok(keys)
+    }
+
+    /// Like [`AuthSdk::parse_jwt_token`], but first fetches the
+    /// [JWKS](crate::SdkBuilder::jwks) if it is stale or lacks the `kid` of
+    /// the token, at most once per [`JwksPolicy::min_refresh_interval`].
+    /// If the fetch fails, the token is still verified with the cached keys
+    /// and the certificate, and the fetch error is returned only if that
+    /// fails too.
+    pub async fn verify_jwt_token(&self, token: &str) -> SdkResult<Claims> {
+        let mut refreshed = Ok(());
+        if let Some(jwks) = self.sdk.jwks() {
+            let (_, kid) = algorithm::decode_header(token)?;
+            if jwks.needs_refresh(kid.as_deref()) {
+                refreshed = jwks.refresh(&self.sdk, kid.as_deref()).await;
+            }
+        }
+        match (self.parse_jwt_token(token), refreshed) {
+            (Err(_), Err(e)) => Err(e),
+            (res, _) => res,
+        }
+    }
+
+    /// Fetches the [JWKS](crate::SdkBuilder::jwks) now, e.g. at startup.
+    pub async fn refresh_jwks(&self) -> SdkResult<()> {
+        match self.sdk.jwks() {
+            Some(jwks) => jwks.refresh_now(&self.sdk).await,
+            None => Err(SdkError::new(StatusCode::BAD_REQUEST, "JWKS is not enabled")),
+        }
+    }
+
+    /// Fetches the claims of the user of the access token from the userinfo
+    /// endpoint.
+    pub async fn get_userinfo(&self, access_token: &str) -> SdkResult<UserInfo> {
+        let (config, endpoints) = self.sdk.snapshot();
+        let url = match self.derived_metadata().await? {
+            Some(metadata) => metadata.userinfo_endpoint.clone().ok_or_else(|| {
+                SdkError::new(
+                    StatusCode::BAD_GATEWAY,
+                    "no userinfo_endpoint in the discovery document",
+                )
+            })?,
+            None => format!("{}/api/userinfo", config.endpoint()),
+        };
+        let mut authorization = HeaderValue::try_from(format!("Bearer {access_token}"))
+            .map_err(|e| SdkError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+        authorization.set_sensitive(true);
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, authorization);
+        let req = HttpRequest {
+            method: Method::GET,
+            url,
+            headers,
+            body: Vec::new(),
+        };
+        let res = self
+            .sdk
+            .send_raw(req, &endpoints, RequestOptions::new().operation("get_userinfo"))
+            .await?;
+        let value: serde_json::Value = match serde_json::from_slice(&res.body) {
+            Ok(value) if res.status.is_success() => value,
+            _ => return Err(SdkError::unexpected_response(&res)),
+        };
+        if value["status"] == "error" {
+            return Err(SdkError::casdoor(value["msg"].as_str().unwrap_or_default()));
+        }
+        Ok(serde_json::from_value(value)?)
     }
 
+    /// The URL the browser is sent to for signing in. With
+    /// [derived](DiscoveryPolicy::derive_urls) URLs, the authorize endpoint
+    /// of the discovery document is used once it is fetched.
     pub fn get_signin_url(&self, redirect_url: String) -> String {
+        let config = self.sdk.config();
+        let authorize_url = match self.sdk.discovery().derived() {
+            Some(metadata) => metadata.authorization_endpoint.clone(),
+            None => format!("{}/login/oauth/authorize", config.browser_endpoint()),
+        };
         let scope = "read";
-        let state = self.sdk.app_name().clone().unwrap_or_default();
+        let state = self.sdk.app_name().unwrap_or_default();
         format!(
-            "{}/login/oauth/authorize?client_id={}&response_type=code&redirect_uri={}&scope={scope}&state={state}",
-            self.sdk.endpoint(),
-            self.sdk.client_id(),
+            "{authorize_url}?client_id={}&response_type=code&redirect_uri={}&scope={scope}&state={state}",
+            config.client_id(),
             urlencoding::encode(&redirect_url).into_owned(),
         )
     }
 
+    /// The URL the browser is sent to for signing out of Casdoor, like
+    /// [`get_signin_url`](AuthSdk::get_signin_url) for the end-session
+    /// endpoint.
+    pub fn get_signout_url(
+        &self,
+        id_token_hint: &str,
+        post_logout_redirect_uri: Option<&str>,
+        state: Option<&str>,
+    ) -> String {
+        let end_session_url = self
+            .sdk
+            .discovery()
+            .derived()
+            .and_then(|metadata| metadata.end_session_endpoint.clone())
+            .unwrap_or_else(|| format!("{}/api/logout", self.sdk.config().browser_endpoint()));
+        let mut url = format!("{end_session_url}?id_token_hint={}", urlencoding::encode(id_token_hint));
+        if let Some(uri) = post_logout_redirect_uri {
+            url.push_str(&format!("&post_logout_redirect_uri={}", urlencoding::encode(uri)));
+        }
+        if let Some(state) = state {
+            url.push_str(&format!("&state={}", urlencoding::encode(state)));
+        }
+        url
+    }
+
     pub fn get_signup_url(&self, redirect_url: String) -> String {
         redirect_url.replace("/login/oauth/authorize", "/signup/oauth/authorize")
     }
@@ -89,8 +388,8 @@ impl AuthSdk {
     pub fn get_signup_url_enable_password(&self) -> String {
         format!(
             "{}/signup/{}",
-            self.sdk.endpoint(),
-            self.sdk.app_name().clone().unwrap_or_default()
+            self.sdk.config().browser_endpoint(),
+            self.sdk.app_name().unwrap_or_default()
         )
     }
 
@@ -99,7 +398,11 @@ impl AuthSdk {
             Some(v) if !v.is_empty() => format!("?access_token={}", v),
             _ => "".to_string(),
         };
-        format!("{}/users/{}/{uname}{param}", self.sdk.endpoint(), self.sdk.org_name())
+        format!(
+            "{}/users/{}/{uname}{param}",
+            self.sdk.config().browser_endpoint(),
+            self.sdk.org_name()
+        )
     }
 
     pub fn get_my_profile_url(&self, token: Option<String>) -> String {
@@ -107,7 +410,7 @@ impl AuthSdk {
             Some(v) if !v.is_empty() => format!("?access_token={}", v),
             _ => "".to_string(),
         };
-        format!("{}/account{}", self.sdk.endpoint(), param)
+        format!("{}/account{}", self.sdk.config().browser_endpoint(), param)
     }
 
     pub async fn get_sessions(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Session>> {
@@ -130,13 +433,183 @@ impl AuthSdk {
         self.sdk
             .request_data(
                 Method::GET,
-                self.sdk.get_url_path("is-session-duplicated", true, [
-                    ("sessionPkId", session_pk_id),
-                    ("sessionId", session_id),
-                ])?,
+                self.sdk.get_url_path(
+                    "is-session-duplicated",
+                    true,
+                    [("sessionPkId", session_pk_id), ("sessionId", session_id)],
+                )?,
                 NO_BODY,
             )
             .await?
             .into_data_default()
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        SdkInnerError, TokenValidationError,
+        test_support::{
+            EC256_CERT, EC256_KEY, EC384_CERT, EC384_KEY, EC521_CERT, EC521_KEY, RSA1_CERT, RSA1_KEY, claims, config,
+            sign_es512,
+        },
+    };
+
+    fn sign(algorithm: Algorithm, key: &EncodingKey) -> String {
+        jsonwebtoken::encode(&Header::new(algorithm), &claims(), key).unwrap()
+    }
+
+    fn authn(certificate: &str, algorithms: &[JwtAlgorithm]) -> AuthSdk {
+        config(certificate)
+            .sdk_builder()
+            .jwt_algorithms(algorithms.iter().copied())
+            .build()
+            .unwrap()
+            .authn()
+    }
+
+    fn is_invalid_algorithm(res: SdkResult<Claims>) -> bool {
+        matches!(res, Err(SdkError { inner: SdkInnerError::JwtError(e), .. }) if *e.kind() == ErrorKind::InvalidAlgorithm)
+    }
+
+    #[test]
+    fn test_jwt_algorithms() {
+        let rsa = EncodingKey::from_rsa_pem(RSA1_KEY.as_bytes()).unwrap();
+        let ec256 = EncodingKey::from_ec_pem(EC256_KEY.as_bytes()).unwrap();
+        let ec384 = EncodingKey::from_ec_pem(EC384_KEY.as_bytes()).unwrap();
+        let cases = [
+            (Algorithm::RS256, &rsa, RSA1_CERT),
+            (Algorithm::RS384, &rsa, RSA1_CERT),
+            (Algorithm::RS512, &rsa, RSA1_CERT),
+            (Algorithm::PS256, &rsa, RSA1_CERT),
+            (Algorithm::PS384, &rsa, RSA1_CERT),
+            (Algorithm::PS512, &rsa, RSA1_CERT),
+            (Algorithm::ES256, &ec256, EC256_CERT),
+            (Algorithm::ES384, &ec384, EC384_CERT),
+        ];
+        for (algorithm, key, certificate) in cases {
+            let token = sign(algorithm, key);
+            let claims = authn(certificate, &DEFAULT_JWT_ALGORITHMS).parse_jwt_token(&token);
+            assert_eq!("alice", claims.unwrap().user.name, "{algorithm:?}");
+            assert!(is_invalid_algorithm(
+                authn(certificate, &[JwtAlgorithm::HS256]).parse_jwt_token(&token)
+            ));
+        }
+
+        let token = sign_es512(EC521_KEY, None, &claims());
+        let es512 = authn(EC521_CERT, &DEFAULT_JWT_ALGORITHMS).parse_jwt_token(&token);
+        assert_eq!("alice", es512.unwrap().user.name);
+        assert!(is_invalid_algorithm(
+            authn(EC521_CERT, &[JwtAlgorithm::ES384]).parse_jwt_token(&token)
+        ));
+        assert!(is_invalid_algorithm(
+            authn(EC384_CERT, &DEFAULT_JWT_ALGORITHMS).parse_jwt_token(&token)
+        ));
+
+        let token = sign(Algorithm::HS256, &EncodingKey::from_secret(b"secret"));
+        assert!(is_invalid_algorithm(
+            authn(RSA1_CERT, &DEFAULT_JWT_ALGORITHMS).parse_jwt_token(&token)
+        ));
+        let claims = authn("", &[JwtAlgorithm::HS256]).parse_jwt_token(&token).unwrap();
+        assert_eq!("alice", claims.user.name);
+        let token = sign(Algorithm::HS256, &EncodingKey::from_secret(b"guessed"));
+        assert!(authn("", &[JwtAlgorithm::HS256]).parse_jwt_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_jwt_algorithms() {
+        let unsupported = |res: SdkResult<crate::Sdk>| match res.map(|_| ()).unwrap_err().inner {
+            SdkInnerError::ConfigError(ConfigError::UnsupportedJwtAlgorithm(name)) => name,
+            e => panic!("unexpected error: {e}"),
+        };
+        let builder = || config(RSA1_CERT).sdk_builder();
+        assert_eq!(
+            "EdDSA",
+            unsupported(builder().jwt_algorithm_names(["RS256", "EdDSA"]).build())
+        );
+        let sdk = builder().jwt_algorithm_names(["RS256", "ES512"]).build().unwrap();
+        assert_eq!(&[JwtAlgorithm::RS256, JwtAlgorithm::ES512], sdk.jwt_algorithms());
+        assert_eq!("ES512", JwtAlgorithm::ES512.to_string());
+    }
+
+    #[test]
+    fn test_jwt_algorithm_confusion() {
+        // An HS256 token signed with the public certificate as the secret.
+        let token = sign(Algorithm::HS256, &EncodingKey::from_secret(RSA1_CERT.as_bytes()));
+        assert!(
+            authn(RSA1_CERT, &[JwtAlgorithm::RS256, JwtAlgorithm::HS256])
+                .parse_jwt_token(&token)
+                .is_err()
+        );
+
+        // An unsigned token.
+        let encode = |value: &serde_json::Value| URL_SAFE_NO_PAD.encode(value.to_string());
+        let token = format!(
+            "{}.{}.",
+            encode(&json!({"alg": "none", "typ": "JWT"})),
+            encode(&claims())
+        );
+        assert!(
+            authn(RSA1_CERT, &DEFAULT_JWT_ALGORITHMS)
+                .parse_jwt_token(&token)
+                .is_err()
+        );
+
+        // Keys of another type or curve are not tried.
+        let rsa = EncodingKey::from_rsa_pem(RSA1_KEY.as_bytes()).unwrap();
+        let ec256 = EncodingKey::from_ec_pem(EC256_KEY.as_bytes()).unwrap();
+        assert!(is_invalid_algorithm(
+            authn(EC256_CERT, &DEFAULT_JWT_ALGORITHMS).parse_jwt_token(&sign(Algorithm::RS256, &rsa))
+        ));
+        assert!(is_invalid_algorithm(
+            authn(EC384_CERT, &DEFAULT_JWT_ALGORITHMS).parse_jwt_token(&sign(Algorithm::ES256, &ec256))
+        ));
+        assert!(is_invalid_algorithm(
+            authn(RSA1_CERT, &[JwtAlgorithm::RS256]).parse_jwt_token(&sign(Algorithm::PS256, &rsa))
+        ));
+    }
+    #[test]
+    fn test_es512() {
+        let authn = authn(EC521_CERT, &DEFAULT_JWT_ALGORITHMS);
+        let token = sign_es512(EC521_KEY, Some("cert-1"), &claims());
+        assert_eq!("alice", authn.parse_jwt_token(&token).unwrap().user.name);
+
+        // A changed payload or signature fails to verify.
+        let (message, signature) = token.rsplit_once('.').unwrap();
+        let (header, _) = message.split_once('.').unwrap();
+        let mut forged = claims();
+        forged["name"] = json!("admin");
+        let forged = format!("{header}.{}.{signature}", URL_SAFE_NO_PAD.encode(forged.to_string()));
+        let is_invalid_signature = |res: SdkResult<Claims>| matches!(res, Err(SdkError { inner: SdkInnerError::JwtError(e), .. }) if *e.kind() == ErrorKind::InvalidSignature);
+        assert!(is_invalid_signature(authn.parse_jwt_token(&forged)));
+        assert!(is_invalid_signature(authn.parse_jwt_token(&format!("{message}.AAAA"))));
+
+        let now = jsonwebtoken::get_current_timestamp();
+        let validation_error = |claims: &serde_json::Value| match authn
+            .parse_jwt_token(&sign_es512(EC521_KEY, None, claims))
+            .unwrap_err()
+            .inner
+        {
+            SdkInnerError::TokenValidation(e) => e,
+            e => panic!("not a validation error: {e}"),
+        };
+        let mut expired = claims();
+        expired["exp"] = json!(now - 120);
+        assert_eq!(TokenValidationError::Expired, validation_error(&expired));
+        let mut immature = claims();
+        immature["nbf"] = json!(now + 120);
+        assert_eq!(TokenValidationError::NotYetValid, validation_error(&immature));
+        let mut unbounded = claims();
+        unbounded.as_object_mut().unwrap().remove("exp");
+        assert_eq!(
+            TokenValidationError::MissingClaim("exp".to_owned()),
+            validation_error(&unbounded)
+        );
+    }
+}
@@ -19,6 +19,29 @@ pub struct Claims {
     pub reg_claims: RegisteredClaims,
 }
 
+/// The OpenID Connect claims of the userinfo endpoint, see
+/// [`AuthSdk::get_userinfo`](super::AuthSdk::get_userinfo).
+#[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct UserInfo {
+    pub sub: String,
+    pub iss: String,
+    pub aud: String,
+    /// The name of the user.
+    pub preferred_username: String,
+    /// The display name of the user.
+    pub name: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub picture: String,
+    pub address: String,
+    pub phone: String,
+    pub groups: Vec<String>,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
 #[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "camelCase", default)]
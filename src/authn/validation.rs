@@ -0,0 +1,319 @@
+use std::{fmt::Display, time::Duration};
+
+use jsonwebtoken::errors::ErrorKind;
+use serde_json::Value;
+
+use super::Claims;
+use crate::SdkError;
+
+/// Controls which JWTs with a valid signature are accepted, see
+/// [`AuthSdk::with_validation_policy`](super::AuthSdk::with_validation_policy)
+/// and [`AuthSdk::parse_jwt_token_with`](super::AuthSdk::parse_jwt_token_with).
+///
+/// The default policy checks the expiry and the not-before time, and that
+/// the audience is the client ID of the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenValidationPolicy {
+    /// The expected `iss`, e.g. the origin of Casdoor. Not checked if `None`.
+    pub issuer: Option<String>,
+    /// The accepted `aud` values, e.g. the client IDs of all applications
+    /// sharing the tokens. The client ID of the config if empty.
+    pub audiences: Vec<String>,
+    /// Clock skew tolerated for `exp`, `nbf` and the max age.
+    pub leeway: Duration,
+    /// Claims that must be present and not empty, e.g. `nonce`, `scope` or
+    /// `tag`.
+    pub required_claims: Vec<String>,
+    /// The longest time since `iat`. Not checked if `None`.
+    pub max_age: Option<Duration>,
+    /// The accepted [`Claims::token_type`]s, such as `access-token`,
+    /// `id-token` or `refresh-token`. Any if empty.
+    pub token_types: Vec<String>,
+}
+
+impl Default for TokenValidationPolicy {
+    fn default() -> Self {
+        Self {
+            issuer: None,
+            audiences: Vec::new(),
+            leeway: Duration::from_secs(60),
+            required_claims: Vec::new(),
+            max_age: None,
+            token_types: Vec::new(),
+        }
+    }
+}
+
+impl TokenValidationPolicy {
+    /// Checks the claims of a token with a valid signature, expiry and
+    /// not-before time.
+    pub(crate) fn validate(&self, raw: &Value, claims: &Claims, client_id: &str) -> Result<(), TokenValidationError> {
+        if let Some(expected) = &self.issuer {
+            let actual = raw.get("iss").and_then(Value::as_str);
+            if actual != Some(expected.as_str()) {
+                return Err(TokenValidationError::InvalidIssuer {
+                    expected: expected.clone(),
+                    actual: actual.map(ToOwned::to_owned),
+                });
+            }
+        }
+
+        let actual: Vec<String> = match raw.get("aud") {
+            Some(Value::String(aud)) => vec![aud.clone()],
+            Some(Value::Array(auds)) => auds.iter().filter_map(Value::as_str).map(ToOwned::to_owned).collect(),
+            _ => Vec::new(),
+        };
+        let accepted = |aud: &String| {
+            if self.audiences.is_empty() {
+                aud == client_id
+            } else {
+                self.audiences.contains(aud)
+            }
+        };
+        if !actual.iter().any(accepted) {
+            return Err(TokenValidationError::InvalidAudience { actual });
+        }
+
+        for claim in &self.required_claims {
+            let present = match raw.get(claim) {
+                None | Some(Value::Null) => false,
+                Some(Value::String(value)) => !value.is_empty(),
+                Some(Value::Array(values)) => !values.is_empty(),
+                Some(_) => true,
+            };
+            if !present {
+                return Err(TokenValidationError::MissingClaim(claim.clone()));
+            }
+        }
+
+        if let Some(max_age) = self.max_age {
+            let issued_at = raw
+                .get("iat")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| TokenValidationError::MissingClaim("iat".to_owned()))?;
+            let age = Duration::from_secs(jsonwebtoken::get_current_timestamp().saturating_sub(issued_at));
+            if age > max_age + self.leeway {
+                return Err(TokenValidationError::TooOld { age, max_age });
+            }
+        }
+
+        if !self.token_types.is_empty()
+            && !claims
+                .token_type
+                .as_ref()
+                .is_some_and(|token_type| self.token_types.contains(token_type))
+        {
+            return Err(TokenValidationError::InvalidTokenType {
+                actual: claims.token_type.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why a JWT with a valid signature is rejected by a
+/// [`TokenValidationPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TokenValidationError {
+    /// `exp` has passed, beyond the leeway.
+    Expired,
+    /// `nbf` has not come yet, beyond the leeway.
+    NotYetValid,
+    InvalidIssuer {
+        expected: String,
+        actual: Option<String>,
+    },
+    /// None of the `aud` values is accepted.
+    InvalidAudience {
+        actual: Vec<String>,
+    },
+    /// A required claim is missing or empty.
+    MissingClaim(String),
+    /// `iat` is longer ago than the max age.
+    TooOld {
+        age: Duration,
+        max_age: Duration,
+    },
+    InvalidTokenType {
+        actual: Option<String>,
+    },
+}
+
+impl TokenValidationError {
+    /// The validation error of a failed [`jsonwebtoken::decode`], or the
+    /// JWT error if the token itself is invalid.
+    pub(crate) fn from_jwt_error(error: jsonwebtoken::errors::Error) -> SdkError {
+        match error.kind() {
+            ErrorKind::ExpiredSignature => Self::Expired.into(),
+            ErrorKind::ImmatureSignature => Self::NotYetValid.into(),
+            ErrorKind::MissingRequiredClaim(claim) => Self::MissingClaim(claim.clone()).into(),
+            _ => error.into(),
+        }
+    }
+}
+
+impl Display for TokenValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expired => write!(f, "token has expired"),
+            Self::NotYetValid => write!(f, "token is not valid yet"),
+            Self::InvalidIssuer { expected, actual: None } => write!(f, "token has no issuer, expected `{expected}`"),
+            Self::InvalidIssuer {
+                expected,
+                actual: Some(actual),
+            } => write!(f, "token issuer `{actual}` is not `{expected}`"),
+            Self::InvalidAudience { actual } => write!(f, "token audience {actual:?} is not accepted"),
+            Self::MissingClaim(claim) => write!(f, "token claim `{claim}` is missing"),
+            Self::TooOld { age, max_age } => {
+                write!(
+                    f,
+                    "token was issued {}s ago, max age is {}s",
+                    age.as_secs(),
+                    max_age.as_secs()
+                )
+            }
+            Self::InvalidTokenType { actual: None } => write!(f, "token has no token type"),
+            Self::InvalidTokenType { actual: Some(actual) } => write!(f, "token type `{actual}` is not accepted"),
+        }
+    }
+}
+
+impl std::error::Error for TokenValidationError {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        AuthSdk, ErrorKind as SdkErrorKind, SdkInnerError, SdkResult, StatusCode,
+        test_support::{RSA1_CERT, RSA1_KEY, RSA2_KEY, config, sign},
+    };
+
+    fn authn() -> AuthSdk {
+        config(RSA1_CERT).into_sdk().authn()
+    }
+
+    /// A token signed with the key, with the claims merged into valid ones.
+    fn token(key: &str, claims: Value) -> String {
+        let now = jsonwebtoken::get_current_timestamp();
+        let mut merged = crate::test_support::claims();
+        merged["iss"] = json!("https://login.example.com");
+        merged["iat"] = json!(now);
+        merged["tokenType"] = json!("access-token");
+        merged["nonce"] = json!("n-0S6_WzA2Mj");
+        merged["scope"] = json!("openid profile");
+        for (name, value) in claims.as_object().unwrap() {
+            merged[name] = value.clone();
+        }
+        sign(key, None, &merged)
+    }
+
+    fn validation_error(res: SdkResult<Claims>) -> TokenValidationError {
+        let err = res.unwrap_err();
+        assert_eq!(StatusCode::UNAUTHORIZED, err.code);
+        assert_eq!(SdkErrorKind::Unauthorized, err.kind());
+        match err.inner {
+            SdkInnerError::TokenValidation(e) => e,
+            e => panic!("not a validation error: {e}"),
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let authn = authn();
+        let now = jsonwebtoken::get_current_timestamp();
+        assert_eq!(
+            "alice",
+            authn.parse_jwt_token(&token(RSA1_KEY, json!({}))).unwrap().user.name
+        );
+        assert!(
+            authn
+                .parse_jwt_token(&token(RSA1_KEY, json!({"exp": now - 30})))
+                .is_ok()
+        );
+        assert_eq!(
+            TokenValidationError::Expired,
+            validation_error(authn.parse_jwt_token(&token(RSA1_KEY, json!({"exp": now - 120}))))
+        );
+        assert_eq!(
+            TokenValidationError::NotYetValid,
+            validation_error(authn.parse_jwt_token(&token(RSA1_KEY, json!({"nbf": now + 600}))))
+        );
+        assert_eq!(
+            TokenValidationError::InvalidAudience {
+                actual: vec!["other".to_owned()]
+            },
+            validation_error(authn.parse_jwt_token(&token(RSA1_KEY, json!({"aud": ["other"]}))))
+        );
+        let err = authn
+            .parse_jwt_token(&token(RSA2_KEY, json!({"exp": now - 120})))
+            .unwrap_err();
+        assert!(matches!(err.inner, SdkInnerError::JwtError(_)));
+    }
+
+    #[test]
+    fn test_validation_policy() {
+        let policy = TokenValidationPolicy {
+            issuer: Some("https://login.example.com".to_owned()),
+            audiences: vec!["app-a".to_owned(), "app-b".to_owned()],
+            required_claims: vec!["nonce".to_owned(), "scope".to_owned()],
+            max_age: Some(Duration::from_secs(600)),
+            token_types: vec!["access-token".to_owned(), "id-token".to_owned()],
+            ..TokenValidationPolicy::default()
+        };
+        let authn = authn().with_validation_policy(policy.clone());
+        let parse = |claims: Value| authn.parse_jwt_token(&token(RSA1_KEY, claims));
+        assert!(parse(json!({"aud": ["app-b"]})).is_ok());
+        assert_eq!(&policy, authn.validation_policy());
+        assert!(
+            authn
+                .parse_jwt_token_with(&token(RSA1_KEY, json!({})), &TokenValidationPolicy::default())
+                .is_ok()
+        );
+
+        assert_eq!(
+            TokenValidationError::InvalidAudience {
+                actual: vec!["cid".to_owned()]
+            },
+            validation_error(parse(json!({})))
+        );
+        assert_eq!(
+            TokenValidationError::InvalidIssuer {
+                expected: "https://login.example.com".to_owned(),
+                actual: Some("https://evil.example.com".to_owned()),
+            },
+            validation_error(parse(json!({"aud": ["app-a"], "iss": "https://evil.example.com"})))
+        );
+        assert_eq!(
+            TokenValidationError::MissingClaim("nonce".to_owned()),
+            validation_error(parse(json!({"aud": ["app-a"], "nonce": null})))
+        );
+        assert_eq!(
+            TokenValidationError::MissingClaim("scope".to_owned()),
+            validation_error(parse(json!({"aud": ["app-a"], "scope": ""})))
+        );
+        let iat = jsonwebtoken::get_current_timestamp() - 3600;
+        let err = validation_error(parse(json!({"aud": ["app-a"], "iat": iat})));
+        assert!(matches!(err, TokenValidationError::TooOld { max_age, .. } if max_age == Duration::from_secs(600)));
+        assert_eq!(
+            TokenValidationError::MissingClaim("iat".to_owned()),
+            validation_error(parse(json!({"aud": ["app-a"], "iat": null})))
+        );
+        assert_eq!(
+            TokenValidationError::InvalidTokenType {
+                actual: Some("refresh-token".to_owned())
+            },
+            validation_error(parse(json!({"aud": ["app-a"], "tokenType": "refresh-token"})))
+        );
+        assert_eq!(
+            "token type `refresh-token` is not accepted",
+            TokenValidationError::InvalidTokenType {
+                actual: Some("refresh-token".to_owned())
+            }
+            .to_string()
+        );
+    }
+}
@@ -2,7 +2,9 @@ mod models;
 
 pub use models::*;
 
-use crate::{Body, Method, NO_BODY, QueryArgs, QueryResult, Sdk, SdkResult};
+use crate::{
+    Body, Idempotency, Method, NO_BODY, OperationClass, QueryArgs, QueryResult, RequestOptions, Sdk, SdkResult,
+};
 
 impl Sdk {
     pub async fn get_enforcers(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Enforcer>> {
@@ -10,10 +12,13 @@ impl Sdk {
     }
     pub async fn enforce(&self, args: EnforceArgs) -> SdkResult<EnforceResult> {
         let allow_list = self
-            .request::<Vec<bool>, Vec<String>>(
+            .request_with::<Vec<bool>, Vec<String>>(
                 Method::POST,
                 self.get_url_path("enforce", true, args.query)?,
                 Body::Json(&args.casbin_request),
+                RequestOptions::new()
+                    .idempotency(Idempotency::Idempotent)
+                    .class(OperationClass::Authz),
             )
             .await?
             .into_data_default()?;
@@ -23,10 +28,13 @@ impl Sdk {
     }
     pub async fn batch_enforce(&self, args: BatchEnforceArgs) -> SdkResult<BatchEnforceResult> {
         let allow_lists = self
-            .request::<Vec<Vec<bool>>, Vec<String>>(
+            .request_with::<Vec<Vec<bool>>, Vec<String>>(
                 Method::POST,
                 self.get_url_path("batch-enforce", true, args.query)?,
                 Body::Json(&args.casbin_requests),
+                RequestOptions::new()
+                    .idempotency(Idempotency::Idempotent)
+                    .class(OperationClass::Authz),
             )
             .await?
             .into_data_default()?;
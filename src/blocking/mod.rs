@@ -0,0 +1,324 @@
+use std::{ops::Deref, sync::Arc};
+
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    ApiCredentials, Application, ApplicationQueryArgs, AuthSdk, BasicTokenType, BatchEnforceArgs, BatchEnforceResult,
+    CasbinRule, Cert, Claims, EnforceArgs, EnforceResult, Enforcer, GetUserArgs, Model, ModelAddArgs, ModelDeleteArgs,
+    ModelModifyArgs, ModelUpdateArgs, Organization, OrganizationQueryArgs, Permission, Provider, ProviderMetadata,
+    QueryArgs, QueryResult, QueryUserSet, Role, Sdk, SdkError, SdkResult, Session, SetPasswordArgs, StatusCode,
+    TokenResponse, TokenValidationPolicy, User, UserGroup, UserGroupQueryArgs, UserInfo, UserQueryArgs,
+};
+
+/// Generates blocking mirrors of async methods of the wrapped SDK.
+macro_rules! blocking_fns {
+    ($($(#[$meta:meta])* fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            $(#[$meta])*
+            ///
+            /// # Panics
+            /// If called from within an async runtime.
+            pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                self.rt.block_on(self.inner.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+/// A synchronous facade over [`Sdk`], driving its requests on an internal
+/// single-threaded runtime.
+///
+/// The async methods of [`Sdk`] are shadowed by blocking ones of the same name;
+/// everything else is reachable through `Deref`.
+#[derive(Debug, Clone)]
+pub struct BlockingSdk {
+    inner: Sdk,
+    rt: Arc<Runtime>,
+}
+
+impl Deref for BlockingSdk {
+    type Target = Sdk;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl Sdk {
+    /// Wraps this Sdk into a [`BlockingSdk`].
+    pub fn into_blocking(self) -> SdkResult<BlockingSdk> {
+        BlockingSdk::new(self)
+    }
+}
+
+impl BlockingSdk {
+    pub fn new(sdk: Sdk) -> SdkResult<Self> {
+        let rt = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| SdkError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        Ok(Self {
+            inner: sdk,
+            rt: Arc::new(rt),
+        })
+    }
+
+    /// See [`Sdk::with_credentials`].
+    pub fn with_credentials(&self, credentials: ApiCredentials) -> Self {
+        self.map(|sdk| sdk.with_credentials(credentials))
+    }
+
+    /// See [`Sdk::with_org`].
+    pub fn with_org(&self, org_name: impl Into<String>) -> Self {
+        self.map(|sdk| sdk.with_org(org_name))
+    }
+
+    /// See [`Sdk::with_app`].
+    pub fn with_app(&self, app_name: impl Into<String>) -> Self {
+        self.map(|sdk| sdk.with_app(app_name))
+    }
+
+    /// A handle to another Sdk that shares the runtime.
+    fn map(&self, f: impl FnOnce(&Sdk) -> Sdk) -> Self {
+        Self {
+            inner: f(&self.inner),
+            rt: self.rt.clone(),
+        }
+    }
+
+    pub fn authn(&self) -> BlockingAuthSdk {
+        BlockingAuthSdk {
+            inner: self.inner.authn(),
+            rt: self.rt.clone(),
+        }
+    }
+
+    /// Blocking version of [`Sdk::modify_model`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn modify_model<T: Model>(&self, args: ModelModifyArgs<T>) -> SdkResult<bool> {
+        self.rt.block_on(self.inner.modify_model(args))
+    }
+
+    /// Blocking version of [`Sdk::add_model`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn add_model<T: Model>(&self, args: ModelAddArgs<T>) -> SdkResult<bool> {
+        self.rt.block_on(self.inner.add_model(args))
+    }
+
+    /// Blocking version of [`Sdk::update_model`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn update_model<T: Model>(&self, args: ModelUpdateArgs<T>) -> SdkResult<bool> {
+        self.rt.block_on(self.inner.update_model(args))
+    }
+
+    /// Blocking version of [`Sdk::delete_model`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn delete_model<T: Model>(&self, args: ModelDeleteArgs<T>) -> SdkResult<bool> {
+        self.rt.block_on(self.inner.delete_model(args))
+    }
+
+    /// Blocking version of [`Sdk::get_model_by_name`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn get_model_by_name<M: Model>(&self, name: String) -> SdkResult<Option<M>> {
+        self.rt.block_on(self.inner.get_model_by_name(name))
+    }
+
+    /// Blocking version of [`Sdk::get_default_model`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn get_default_model<M: Model>(&self, name: String) -> SdkResult<Option<M>> {
+        self.rt.block_on(self.inner.get_default_model(name))
+    }
+
+    blocking_fns! {
+        /// Blocking version of [`Sdk::get_users`].
+        fn get_users(&self, query_args: UserQueryArgs) -> SdkResult<QueryResult<User>>;
+        /// Blocking version of [`Sdk::get_user_groups`].
+        fn get_user_groups(&self, query_args: UserGroupQueryArgs) -> SdkResult<QueryResult<UserGroup>>;
+        /// Blocking version of [`Sdk::get_user_count`].
+        fn get_user_count(&self, is_online: QueryUserSet) -> SdkResult<i64>;
+        /// Blocking version of [`Sdk::get_user`].
+        fn get_user(&self, args: GetUserArgs) -> SdkResult<Option<User>>;
+        /// Blocking version of [`Sdk::get_user_by_email`].
+        fn get_user_by_email(&self, email: String) -> SdkResult<Option<User>>;
+        /// Blocking version of [`Sdk::get_user_by_phone`].
+        fn get_user_by_phone(&self, phone: String) -> SdkResult<Option<User>>;
+        /// Blocking version of [`Sdk::get_user_by_user_id`].
+        fn get_user_by_user_id(&self, user_id: String) -> SdkResult<Option<User>>;
+        /// Blocking version of [`Sdk::set_user_password`].
+        fn set_user_password(&self, args: SetPasswordArgs) -> SdkResult<()>;
+
+        /// Blocking version of [`Sdk::get_enforcers`].
+        fn get_enforcers(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Enforcer>>;
+        /// Blocking version of [`Sdk::enforce`].
+        fn enforce(&self, args: EnforceArgs) -> SdkResult<EnforceResult>;
+        /// Blocking version of [`Sdk::batch_enforce`].
+        fn batch_enforce(&self, args: BatchEnforceArgs) -> SdkResult<BatchEnforceResult>;
+        /// Blocking version of [`Sdk::get_policies`].
+        fn get_policies(&self, enforcer_name: String) -> SdkResult<Vec<CasbinRule>>;
+        /// Blocking version of [`Sdk::add_policy`].
+        fn add_policy(&self, enforcer_name: String, policy: &CasbinRule) -> SdkResult<bool>;
+        /// Blocking version of [`Sdk::remove_policy`].
+        fn remove_policy(&self, enforcer_name: String, policy: &CasbinRule) -> SdkResult<bool>;
+        /// Blocking version of [`Sdk::update_policy`].
+        fn update_policy(
+            &self,
+            enforcer_name: String,
+            old_policy: &CasbinRule,
+            new_policy: &CasbinRule
+        ) -> SdkResult<bool>;
+        /// Blocking version of [`Sdk::get_permissions`].
+        fn get_permissions(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Permission>>;
+        /// Blocking version of [`Sdk::get_permissions_by_submitter`].
+        fn get_permissions_by_submitter(&self) -> SdkResult<QueryResult<Permission>>;
+        /// Blocking version of [`Sdk::get_permissions_by_role`].
+        fn get_permissions_by_role(&self, role_name: &str) -> SdkResult<QueryResult<Permission>>;
+        /// Blocking version of [`Sdk::get_roles`].
+        fn get_roles(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Role>>;
+        /// Blocking version of [`Sdk::get_roles_by_user`].
+        fn get_roles_by_user(&self, user_id: &str) -> SdkResult<Vec<String>>;
+
+        /// Blocking version of [`Sdk::get_user_application`].
+        fn get_user_application(&self, user_name: &str) -> SdkResult<Option<Application>>;
+        /// Blocking version of [`Sdk::get_applications`].
+        fn get_applications(&self, query_args: ApplicationQueryArgs) -> SdkResult<QueryResult<Application>>;
+        /// Blocking version of [`Sdk::get_organization_applications`].
+        fn get_organization_applications(
+            &self,
+            query_args: ApplicationQueryArgs
+        ) -> SdkResult<QueryResult<Application>>;
+
+        /// Blocking version of [`Sdk::get_default_organization`].
+        fn get_default_organization(&self, name: String) -> SdkResult<Option<Organization>>;
+        /// Blocking version of [`Sdk::get_organizations`].
+        fn get_organizations(&self, query_args: OrganizationQueryArgs) -> SdkResult<QueryResult<Organization>>;
+        /// Blocking version of [`Sdk::get_organization_names`].
+        fn get_organization_names(&self) -> SdkResult<Vec<Organization>>;
+
+        /// Blocking version of [`Sdk::get_certs`].
+        fn get_certs(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Cert>>;
+        /// Blocking version of [`Sdk::get_global_certs`].
+        fn get_global_certs(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Cert>>;
+
+        /// Blocking version of [`Sdk::get_providers`].
+        fn get_providers(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Provider>>;
+        /// Blocking version of [`Sdk::get_global_providers`].
+        fn get_global_providers(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Provider>>;
+    }
+}
+
+/// A synchronous facade over [`AuthSdk`], see [`BlockingSdk`].
+#[derive(Debug, Clone)]
+pub struct BlockingAuthSdk {
+    inner: AuthSdk,
+    rt: Arc<Runtime>,
+}
+
+impl Deref for BlockingAuthSdk {
+    type Target = AuthSdk;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl BlockingAuthSdk {
+    /// See [`AuthSdk::with_validation_policy`].
+    pub fn with_validation_policy(&self, policy: TokenValidationPolicy) -> Self {
+        Self {
+            inner: self.inner.with_validation_policy(policy),
+            rt: self.rt.clone(),
+        }
+    }
+
+    /// Blocking version of [`AuthSdk::get_oauth_token`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn get_oauth_token(&self, code: String) -> SdkResult<impl TokenResponse<BasicTokenType>> {
+        self.rt.block_on(self.inner.get_oauth_token(code))
+    }
+
+    /// Blocking version of [`AuthSdk::refresh_oauth_token`].
+    ///
+    /// # Panics
+    /// If called from within an async runtime.
+    pub fn refresh_oauth_token(&self, refresh_token: String) -> SdkResult<impl TokenResponse<BasicTokenType>> {
+        self.rt.block_on(self.inner.refresh_oauth_token(refresh_token))
+    }
+
+    blocking_fns! {
+        /// Blocking version of [`AuthSdk::discover`].
+        fn discover(&self) -> SdkResult<Arc<ProviderMetadata>>;
+        /// Blocking version of [`AuthSdk::get_userinfo`].
+        fn get_userinfo(&self, access_token: &str) -> SdkResult<UserInfo>;
+        /// Blocking version of [`AuthSdk::verify_jwt_token`].
+        fn verify_jwt_token(&self, token: &str) -> SdkResult<Claims>;
+        /// Blocking version of [`AuthSdk::refresh_jwks`].
+        fn refresh_jwks(&self) -> SdkResult<()>;
+        /// Blocking version of [`AuthSdk::get_sessions`].
+        fn get_sessions(&self, query_args: QueryArgs) -> SdkResult<QueryResult<Session>>;
+        /// Blocking version of [`AuthSdk::get_session`].
+        fn get_session(&self, session_pk_id: &str) -> SdkResult<Session>;
+        /// Blocking version of [`AuthSdk::is_session_duplicated`].
+        fn is_session_duplicated(&self, session_pk_id: &str, session_id: &str) -> SdkResult<bool>;
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::default_numeric_fallback)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{CasbinRule, EnforceArgs, QueryUserSet, TokenResponse, test_support::mock_sdk};
+
+    #[test]
+    fn test_blocking_sdk() {
+        let (sdk, transport) = mock_sdk();
+        let sdk = sdk.into_blocking().unwrap();
+        transport
+            .push_json(&json!({"status": "ok", "msg": "", "data": 7}))
+            .push_json(&json!({"status": "ok", "msg": "", "data": [true]}))
+            .push_json(&json!({"status": "ok", "msg": "", "data": true}))
+            .push_json(&json!({"access_token": "token", "token_type": "bearer"}));
+        assert_eq!(7, sdk.get_user_count(QueryUserSet::Online).unwrap());
+        assert!(sdk.enforce(EnforceArgs::default()).unwrap().allow);
+        assert!(sdk.add_policy("enforcer".to_owned(), &CasbinRule::default()).unwrap());
+        let authn = sdk.authn();
+        assert_eq!(
+            "token",
+            authn
+                .get_oauth_token("code".to_owned())
+                .unwrap()
+                .access_token()
+                .secret()
+        );
+        assert!(
+            authn
+                .get_signup_url_enable_password()
+                .starts_with("http://casdoor.test/signup/")
+        );
+
+        let urls = transport.requests().into_iter().map(|req| req.url).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "http://casdoor.test/api/get-user-count?owner=built-in&isOnline=1",
+                "http://casdoor.test/api/enforce?owner=built-in",
+                "http://casdoor.test/api/add-policy?&id=built-in%2Fenforcer",
+                "http://casdoor.test/api/login/oauth/access_token",
+            ],
+            urls
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::Model;
+use crate::{Model, Secret};
 
 #[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -18,7 +18,7 @@ pub struct Cert {
     expire_in_years: i32,
 
     certificate: String,
-    private_key: String,
+    private_key: Secret,
     authority_public_key: String,
     authority_root_public_key: String,
 }
@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use super::{ApiCredentials, Config, ConfigError};
+use crate::Secret;
+
+/// Builder for a validated [`Config`], see [`Config::builder`].
+///
+/// It is also what [`Config::parse`] and [`Config::from_file`] deserialize,
+/// so config files use the same field names, e.g. `certificate_file`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConfigBuilder {
+    endpoint: Option<String>,
+    #[serde(default)]
+    fallback_endpoints: Vec<String>,
+    public_endpoint: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<Secret>,
+    certificate: Option<String>,
+    certificate_file: Option<PathBuf>,
+    org_name: Option<String>,
+    app_name: Option<String>,
+    #[serde(default)]
+    credentials: ApiCredentials,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Casdoor Server Url, such as `http://localhost:8000`. Required.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// See [`Config::with_fallback_endpoints`].
+    pub fn fallback_endpoints(mut self, endpoints: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.fallback_endpoints = endpoints.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// See [`Config::with_public_endpoint`].
+    pub fn public_endpoint(mut self, public_endpoint: impl Into<String>) -> Self {
+        self.public_endpoint = Some(public_endpoint.into());
+        self
+    }
+
+    /// Client ID for the Casdoor application. Required.
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Client secret for the Casdoor application. Required with
+    /// [`ApiCredentials::ClientBasic`].
+    pub fn client_secret(mut self, client_secret: impl Into<String>) -> Self {
+        self.client_secret = Some(Secret::new(client_secret.into()));
+        self
+    }
+
+    /// x509 certificate content of Application.cert.
+    pub fn certificate(mut self, certificate: impl Into<String>) -> Self {
+        self.certificate = Some(certificate.into());
+        self
+    }
+
+    /// Read the certificate from the file when building.
+    pub fn certificate_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.certificate_file = Some(path.into());
+        self
+    }
+
+    /// The name for the Casdoor organization. Required.
+    pub fn org_name(mut self, org_name: impl Into<String>) -> Self {
+        self.org_name = Some(org_name.into());
+        self
+    }
+
+    /// The name for the Casdoor application.
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// See [`Config::with_credentials`].
+    pub fn credentials(mut self, credentials: ApiCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Reads the certificate file if set and returns the
+    /// [validated](Config::validate) config.
+    pub fn build(self) -> Result<Config, ConfigError> {
+        let certificate = match (self.certificate, self.certificate_file) {
+            (Some(_), Some(_)) => return Err(ConfigError::ConflictingFields("certificate", "certificate_file")),
+            (Some(certificate), None) => certificate,
+            (None, Some(path)) => std::fs::read_to_string(&path).map_err(|source| ConfigError::Io { path, source })?,
+            (None, None) => String::new(),
+        };
+        let mut config = Config::new(
+            trim_endpoint(required("endpoint", self.endpoint)?),
+            required("client_id", self.client_id)?,
+            String::new(),
+            certificate,
+            required("org_name", self.org_name)?,
+            self.app_name.filter(|app_name| !app_name.is_empty()),
+        )
+        .with_fallback_endpoints(self.fallback_endpoints.into_iter().map(trim_endpoint))
+        .with_credentials(self.credentials);
+        config.client_secret = self.client_secret.unwrap_or_default();
+        config.public_endpoint = self.public_endpoint.map(trim_endpoint);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads the fields from the `{prefix}_*` environment variables, see
+    /// [`Config::from_env`].
+    pub(super) fn from_env(
+        prefix: &str,
+        var: impl Fn(&str) -> Result<Option<String>, ConfigError>,
+    ) -> Result<Self, ConfigError> {
+        let var = |name: &str| var(&format!("{prefix}_{name}"));
+        Ok(Self {
+            endpoint: var("ENDPOINT")?,
+            fallback_endpoints: var("FALLBACK_ENDPOINTS")?
+                .map(|endpoints| {
+                    endpoints
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|endpoint| !endpoint.is_empty())
+                        .map(ToOwned::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            public_endpoint: var("PUBLIC_ENDPOINT")?,
+            client_id: var("CLIENT_ID")?,
+            client_secret: var("CLIENT_SECRET")?.map(Secret::new),
+            certificate: var("CERTIFICATE")?,
+            certificate_file: var("CERTIFICATE_FILE")?.map(PathBuf::from),
+            org_name: var("ORG_NAME")?,
+            app_name: var("APP_NAME")?,
+            credentials: ApiCredentials::default(),
+        })
+    }
+
+    /// Resolves a relative certificate file against the directory of the
+    /// config file.
+    pub(super) fn relative_to(mut self, dir: &std::path::Path) -> Self {
+        if let Some(path) = &mut self.certificate_file {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        }
+        self
+    }
+}
+
+fn required(field: &'static str, value: Option<String>) -> Result<String, ConfigError> {
+    value
+        .filter(|value| !value.is_empty())
+        .ok_or(ConfigError::MissingField(field))
+}
+
+fn trim_endpoint(endpoint: String) -> String {
+    match endpoint.strip_suffix('/') {
+        Some(trimmed) => trimmed.trim_end_matches('/').to_owned(),
+        None => endpoint,
+    }
+}
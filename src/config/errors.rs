@@ -0,0 +1,59 @@
+use std::{fmt::Display, path::PathBuf};
+
+use super::ConfigFormat;
+
+/// Why a [`Config`](crate::Config) could not be loaded or is invalid.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A config or certificate file could not be read.
+    Io { path: PathBuf, source: std::io::Error },
+    /// The content is not valid in its format.
+    Parse { format: ConfigFormat, message: String },
+    /// The format of the file cannot be told from its extension.
+    UnknownFormat(PathBuf),
+    /// A required field is missing or empty.
+    MissingField(&'static str),
+    /// Two fields that exclude each other are both set.
+    ConflictingFields(&'static str, &'static str),
+    /// An environment variable is not valid unicode.
+    InvalidEnv(String),
+    /// A field is not an absolute http(s) URL.
+    InvalidUrl {
+        field: &'static str,
+        url: String,
+        reason: String,
+    },
+    /// The certificate is not an X.509 certificate or public key of a
+    /// supported type.
+    InvalidCertificate(String),
+    /// JWTs signed with the algorithm, such as EdDSA, cannot be verified.
+    UnsupportedJwtAlgorithm(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "failed to read {}: {source}", path.display()),
+            ConfigError::Parse { format, message } => write!(f, "invalid {format} config: {message}"),
+            ConfigError::UnknownFormat(path) => write!(f, "unknown config format of {}", path.display()),
+            ConfigError::MissingField(field) => write!(f, "missing config field `{field}`"),
+            ConfigError::ConflictingFields(a, b) => write!(f, "config fields `{a}` and `{b}` are both set"),
+            ConfigError::InvalidEnv(name) => write!(f, "environment variable {name} is not valid unicode"),
+            ConfigError::InvalidUrl { field, url, reason } => {
+                write!(f, "config field `{field}` is not a valid URL ({reason}): {url}")
+            }
+            ConfigError::InvalidCertificate(reason) => write!(f, "invalid certificate: {reason}"),
+            ConfigError::UnsupportedJwtAlgorithm(name) => write!(f, "JWT algorithm `{name}` is not supported"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,406 @@
+mod builder;
+mod certificate;
+mod errors;
+use std::{fmt::Display, path::Path};
+
+pub use builder::*;
+pub use certificate::{CertificateInfo, KeyType};
+pub(crate) use certificate::{ParsedCertificate, VerificationKey};
+use cubix::getset2::Getset2;
+pub use errors::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{Secret, Url};
+
+/// Config is the core configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, Getset2)]
+#[getset2(get_ref(pub))]
+pub struct Config {
+    /// Casdoor Server Url, such as `http://localhost:8000`
+    endpoint: String,
+    /// Urls of other Casdoor servers to fail over to, in order, see
+    /// [`FailoverPolicy`](crate::FailoverPolicy)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fallback_endpoints: Vec<String>,
+    /// Url of the Casdoor server as seen by browsers, such as
+    /// `https://login.example.com`, when it differs from the endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_endpoint: Option<String>,
+    /// Client ID for the Casdoor application
+    client_id: String,
+    /// Client secret for the Casdoor application
+    client_secret: Secret,
+    /// x509 certificate content of Application.cert, see
+    /// [`Config::certificate_info`]
+    certificate: String,
+    /// The name for the Casdoor organization
+    org_name: String,
+    /// The name for the Casdoor application
+    app_name: Option<String>,
+    /// How API requests authenticate against Casdoor
+    #[serde(default)]
+    credentials: ApiCredentials,
+}
+
+/// The credentials sent with API requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApiCredentials {
+    /// HTTP basic auth with the client ID and secret of the application.
+    #[default]
+    ClientBasic,
+    /// The `accessKey`/`accessSecret` pair of a user, sent as query
+    /// parameters.
+    AccessKey { access_key: String, access_secret: Secret },
+    /// An access token of a logged-in user, sent as a bearer token.
+    Bearer { token: Secret },
+}
+
+/// The format of a config file, see [`Config::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    /// Requires the `yaml` feature.
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// The format of a file by its extension: `toml`, `json`, or `yaml` and
+    /// `yml` with the `yaml` feature.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ConfigFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Toml => "TOML",
+            Self::Json => "JSON",
+            #[cfg(feature = "yaml")]
+            Self::Yaml => "YAML",
+        })
+    }
+}
+
+impl Config {
+    /// Create a new Config. Use [`Config::builder`] for a validated one.
+    pub fn new(
+        endpoint: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        certificate: impl Into<String>,
+        org_name: impl Into<String>,
+        app_name: Option<String>,
+    ) -> Self {
+        Config {
+            endpoint: endpoint.into(),
+            fallback_endpoints: Vec::new(),
+            public_endpoint: None,
+            client_id: client_id.into(),
+            client_secret: Secret::new(client_secret.into()),
+            certificate: certificate.into(),
+            org_name: org_name.into(),
+            app_name,
+            credentials: ApiCredentials::default(),
+        }
+    }
+
+    /// Authenticate API requests with the given credentials instead of the
+    /// client ID and secret.
+    pub fn with_credentials(mut self, credentials: ApiCredentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Fail over to the given Casdoor servers, in order, when the endpoint
+    /// is unreachable.
+    pub fn with_fallback_endpoints(mut self, endpoints: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.fallback_endpoints = endpoints.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The endpoint followed by the fallback endpoints.
+    pub fn endpoints(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.endpoint).chain(&self.fallback_endpoints)
+    }
+
+    /// Redirect browsers to the given Casdoor server Url instead of the
+    /// endpoint, which is then only used for API and token calls.
+    pub fn with_public_endpoint(mut self, public_endpoint: impl Into<String>) -> Self {
+        self.public_endpoint = Some(public_endpoint.into());
+        self
+    }
+
+    /// The Url of the Casdoor server used in browser URLs: the public
+    /// endpoint if set, else the endpoint.
+    pub fn browser_endpoint(&self) -> &String {
+        self.public_endpoint.as_ref().unwrap_or(&self.endpoint)
+    }
+
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+
+    /// Create a new Config from the `{prefix}_*` environment variables:
+    /// `ENDPOINT`, `FALLBACK_ENDPOINTS` (comma separated), `PUBLIC_ENDPOINT`,
+    /// `CLIENT_ID`, `CLIENT_SECRET`, `CERTIFICATE` or `CERTIFICATE_FILE`,
+    /// `ORG_NAME` and `APP_NAME`, e.g. `CASDOOR_ENDPOINT` for the prefix
+    /// `CASDOOR`. Empty variables count as unset.
+    pub fn from_env(prefix: &str) -> Result<Self, ConfigError> {
+        ConfigBuilder::from_env(prefix, |name| match std::env::var(name) {
+            Ok(value) => Ok(Some(value).filter(|value| !value.is_empty())),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(std::env::VarError::NotUnicode(_)) => Err(ConfigError::InvalidEnv(name.to_owned())),
+        })?
+        .build()
+    }
+
+    /// Create a new Config from the content of a config file, see
+    /// [`ConfigBuilder`] for its fields. A relative `certificate_file` is
+    /// resolved against the current directory.
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self, ConfigError> {
+        Self::parse_builder(content, format)?.build()
+    }
+
+    fn parse_builder(content: &str, format: ConfigFormat) -> Result<ConfigBuilder, ConfigError> {
+        let parse_error = |message: String| ConfigError::Parse { format, message };
+        match format {
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| parse_error(e.to_string())),
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| parse_error(e.to_string())),
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| parse_error(e.to_string())),
+        }
+    }
+
+    /// Create a new Config from a TOML, JSON or YAML file, by its extension.
+    /// A relative `certificate_file` is resolved against the directory of the
+    /// file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path).ok_or_else(|| ConfigError::UnknownFormat(path.to_owned()))?;
+        Self::from_file_with(path, format)
+    }
+
+    /// Create a new Config from a Toml file.
+    pub fn from_toml(path: &str) -> Result<Self, ConfigError> {
+        Self::from_file_with(Path::new(path), ConfigFormat::Toml)
+    }
+
+    fn from_file_with(path: &Path, format: ConfigFormat) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::parse_builder(&content, format)?
+            .relative_to(path.parent().unwrap_or_else(|| Path::new("")))
+            .build()
+    }
+
+    /// Checks that the endpoints are http(s) URLs, the required fields are
+    /// set and the certificate, if any, can be parsed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.endpoint.is_empty() {
+            return Err(ConfigError::MissingField("endpoint"));
+        }
+        validate_url("endpoint", &self.endpoint)?;
+        for endpoint in &self.fallback_endpoints {
+            validate_url("fallback_endpoints", endpoint)?;
+        }
+        if let Some(public_endpoint) = &self.public_endpoint {
+            validate_url("public_endpoint", public_endpoint)?;
+        }
+        if self.client_id.is_empty() {
+            return Err(ConfigError::MissingField("client_id"));
+        }
+        if self.client_secret.is_empty() && self.credentials == ApiCredentials::ClientBasic {
+            return Err(ConfigError::MissingField("client_secret"));
+        }
+        if self.org_name.is_empty() {
+            return Err(ConfigError::MissingField("org_name"));
+        }
+        if !self.certificate.is_empty() {
+            ParsedCertificate::parse(&self.certificate)?;
+        }
+        Ok(())
+    }
+
+    /// Metadata of the certificate, `None` without one or for a bare public
+    /// key. The certificate may be PEM, where the first certificate of a
+    /// chain is used, or the base64 of its DER encoding.
+    pub fn certificate_info(&self) -> Result<Option<CertificateInfo>, ConfigError> {
+        if self.certificate.is_empty() {
+            return Ok(None);
+        }
+        Ok(ParsedCertificate::parse(&self.certificate)?.info)
+    }
+}
+
+fn validate_url(field: &'static str, url: &str) -> Result<(), ConfigError> {
+    let invalid = |reason: String| ConfigError::InvalidUrl {
+        field,
+        url: url.to_owned(),
+        reason,
+    };
+    let parsed = Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid("the scheme is not http or https".to_owned()));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(invalid("no host".to_owned()));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(invalid("it has a query or fragment".to_owned()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::tests::CERTIFICATE;
+
+    fn builder() -> ConfigBuilder {
+        Config::builder()
+            .endpoint("http://casdoor.auth.svc:8000/")
+            .client_id("cid")
+            .client_secret("secret")
+            .certificate(CERTIFICATE)
+            .org_name("built-in")
+    }
+
+    #[test]
+    fn test_config_builder() {
+        let config = builder()
+            .public_endpoint("https://login.example.com")
+            .app_name("app")
+            .build()
+            .unwrap();
+        assert_eq!("http://casdoor.auth.svc:8000", config.endpoint());
+        assert_eq!("https://login.example.com", config.browser_endpoint());
+        assert_eq!(&Some("app".to_owned()), config.app_name());
+        let info = config.certificate_info().unwrap().unwrap();
+        assert_eq!("O=Casdoor Organization, CN=Casdoor Cert", info.subject);
+        assert_eq!(KeyType::Rsa, info.key_type);
+
+        let err = builder().client_id("").build().unwrap_err();
+        assert!(matches!(err, ConfigError::MissingField("client_id")));
+        assert_eq!("missing config field `client_id`", err.to_string());
+        assert!(matches!(
+            builder().client_secret("").build(),
+            Err(ConfigError::MissingField("client_secret"))
+        ));
+        builder()
+            .client_secret("")
+            .credentials(ApiCredentials::Bearer { token: "t".into() })
+            .build()
+            .unwrap();
+        for endpoint in ["localhost:8000", "ftp://casdoor.test", "http://casdoor.test?a=b"] {
+            assert!(matches!(
+                builder().endpoint(endpoint).build(),
+                Err(ConfigError::InvalidUrl { field: "endpoint", .. })
+            ));
+        }
+        assert!(matches!(
+            builder().fallback_endpoints(["not a url"]).build(),
+            Err(ConfigError::InvalidUrl {
+                field: "fallback_endpoints",
+                ..
+            })
+        ));
+        assert!(matches!(
+            builder()
+                .certificate("-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----")
+                .build(),
+            Err(ConfigError::InvalidCertificate(_))
+        ));
+        assert!(matches!(
+            builder().certificate_file("cert.pem").build(),
+            Err(ConfigError::ConflictingFields("certificate", "certificate_file"))
+        ));
+    }
+
+    #[test]
+    fn test_config_files() {
+        let toml = r#"
+            endpoint = "http://casdoor.test"
+            client_id = "cid"
+            client_secret = "secret"
+            org_name = "built-in"
+            credentials = { type = "bearer", token = "t" }
+        "#;
+        let config = Config::parse(toml, ConfigFormat::Toml).unwrap();
+        assert_eq!(&ApiCredentials::Bearer { token: "t".into() }, config.credentials());
+        let json = r#"{"endpoint": "http://casdoor.test", "client_id": "cid", "org_name": "built-in"}"#;
+        let err = Config::parse(json, ConfigFormat::Json).unwrap_err();
+        assert!(matches!(err, ConfigError::MissingField("client_secret")));
+        let err = Config::parse("{", ConfigFormat::Json).unwrap_err();
+        assert!(err.to_string().starts_with("invalid JSON config: "));
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        std::fs::write(dir.join("cert.pem"), CERTIFICATE).unwrap();
+        let path = dir.join("casdoor.toml");
+        std::fs::write(&path, format!("{toml}certificate_file = \"cert.pem\"\n")).unwrap();
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(CERTIFICATE, config.certificate());
+        assert!(config.certificate_info().unwrap().is_some());
+        assert!(matches!(
+            Config::from_file(dir.join("casdoor.ini")),
+            Err(ConfigError::UnknownFormat(_))
+        ));
+        assert!(matches!(
+            Config::from_toml(dir.join("missing.toml").to_str().unwrap()),
+            Err(ConfigError::Io { .. })
+        ));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_config_yaml() {
+        let yaml = "endpoint: http://casdoor.test\nclient_id: cid\nclient_secret: secret\norg_name: built-in\n";
+        let config = Config::parse(yaml, ConfigFormat::Yaml).unwrap();
+        assert_eq!("http://casdoor.test", config.endpoint());
+        assert_eq!(Some(ConfigFormat::Yaml), ConfigFormat::from_path("casdoor.yml"));
+    }
+
+    #[test]
+    fn test_config_from_env() {
+        let vars = HashMap::from([
+            ("CASDOOR_ENDPOINT", "http://casdoor.test"),
+            (
+                "CASDOOR_FALLBACK_ENDPOINTS",
+                "http://a.casdoor.test, http://b.casdoor.test",
+            ),
+            ("CASDOOR_CLIENT_ID", "cid"),
+            ("CASDOOR_CLIENT_SECRET", "secret"),
+            ("CASDOOR_ORG_NAME", "built-in"),
+            ("OTHER_ORG_NAME", "other"),
+        ]);
+        let config = ConfigBuilder::from_env("CASDOOR", |name| Ok(vars.get(name).map(|v| (*v).to_owned())))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            vec!["http://casdoor.test", "http://a.casdoor.test", "http://b.casdoor.test"],
+            config.endpoints().collect::<Vec<_>>()
+        );
+        assert_eq!("built-in", config.org_name());
+        assert_eq!(&None, config.app_name());
+        assert!(matches!(
+            Config::from_env("CASDOOR_SDK_UNSET_PREFIX"),
+            Err(ConfigError::MissingField("endpoint"))
+        ));
+    }
+}
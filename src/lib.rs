@@ -92,29 +92,40 @@
 #![warn(clippy::unused_async)]
 // [REQUIRED] G.UNS.SAS.02 Use `assert!` instead of `debug_assert!` to verify boundary conditions in unsafe functions
 #![warn(clippy::debug_assert_with_mut_call)]
+// [REQUIRED] The SDK does not need any unsafe code
+#![forbid(unsafe_code)]
 
 mod application;
 mod authn;
 mod authz;
+#[cfg(feature = "blocking")]
+mod blocking;
 mod cert;
 mod config;
 mod organization;
 mod provider;
 mod sdk;
+mod secret;
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+pub(crate) mod test_support;
 mod user;
 pub mod utils;
 
 pub use application::*;
 pub use authn::*;
 pub use authz::*;
+#[cfg(feature = "blocking")]
+pub use blocking::*;
 #[cfg(feature = "api")]
 pub use casdoor_api::{apis, models as api_models};
 pub use cert::*;
 pub use config::*;
 pub use organization::*;
 pub use provider::*;
-pub use reqwest::{Method, StatusCode, Url};
+pub use reqwest::{Certificate, Method, Proxy, StatusCode, Url, header};
 pub use sdk::*;
+pub use secret::*;
 pub use user::*;
 
 pub type SdkResult<T> = std::result::Result<T, SdkError>;
@@ -122,12 +133,8 @@ pub type SdkResult<T> = std::result::Result<T, SdkError>;
 #[cfg(test)]
 mod tests {
     use crate::*;
-    #[test]
-    fn example() {
-        let endpoint = "http://localhost:8000";
-        let client_id = "0e6ad201d317fb74fe9d";
-        let client_secret = "1fc847b0fdb3cb3f067c15ee383dee6213bd3fde";
-        let certificate = r###"
+
+    pub(crate) const CERTIFICATE: &str = r###"
 -----BEGIN CERTIFICATE-----
 MIIE+TCCAuGgAwIBAgIDAeJAMA0GCSqGSIb3DQEBCwUAMDYxHTAbBgNVBAoTFENh
 c2Rvb3IgT3JnYW5pemF0aW9uMRUwEwYDVQQDEwxDYXNkb29yIENlcnQwHhcNMjEx
@@ -158,6 +165,13 @@ nCCJHBcAyFnm1hdvdwEdH33jDBjNB6ciotJZrf/3VYaIWSalADosHAgMWfXuWP+h
 8XKXmzlxuHbTMQYtZPDgspS5aK+S4Q9wb8RRAYo=
 -----END CERTIFICATE-----
 "###;
+
+    #[test]
+    fn example() {
+        let endpoint = "http://localhost:8000";
+        let client_id = "0e6ad201d317fb74fe9d";
+        let client_secret = "1fc847b0fdb3cb3f067c15ee383dee6213bd3fde";
+        let certificate = CERTIFICATE;
         let org_name = "built-in";
         let app_name = "myapp";
 
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{IsQueryArgs, Model};
+use crate::{IsQueryArgs, Model, Secret};
 #[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "camelCase", default)]
@@ -21,7 +21,7 @@ pub struct Organization {
     tags: Vec<String>,
     languages: Vec<String>,
     theme_data: Option<ThemeData>,
-    master_password: String,
+    master_password: Secret,
     init_score: i32,
     enable_soft_deletion: bool,
     is_profile_public: bool,
@@ -99,8 +99,25 @@ pub struct OrganizationQueryArgs {
     #[serde(rename = "sortOrder", skip_serializing_if = "Option::is_none")]
     pub sort_order: Option<String>,
     #[cfg_attr(feature = "salvo", salvo(parameter(parameter_in=Query,required=false)))]
-    #[serde(rename = "sortOrder", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "organizationName", skip_serializing_if = "Option::is_none")]
     pub organization_name: Option<String>,
 }
 
 impl IsQueryArgs for OrganizationQueryArgs {}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_organization_secrets() {
+        let organization: Organization = serde_json::from_str(
+            r#"{"owner": "admin", "name": "built-in", "masterPassword": "example_master_password"}"#,
+        )
+        .unwrap();
+        assert!(!format!("{organization:?}").contains("example_master_password"));
+        let value = serde_json::to_value(&organization).unwrap();
+        assert_eq!("example_master_password", value["masterPassword"]);
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::Model;
+use crate::{Model, Secret};
 
 #[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -18,9 +18,9 @@ pub struct Provider {
     sub_type: String,
     method: String,
     client_id: String,
-    client_secret: String,
+    client_secret: Secret,
     client_id2: String,
-    client_secret2: String,
+    client_secret2: Secret,
     cert: String,
     custom_auth_url: String,
     custom_token_url: String,
@@ -0,0 +1,193 @@
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{SdkError, SdkInnerError, SdkResult, StatusCode};
+
+/// Stops sending requests to Casdoor while it looks unavailable.
+///
+/// The circuit opens after `failure_threshold` consecutive transport failures
+/// or 5xx responses. While open, requests fail fast with an error for which
+/// [`SdkError::is_circuit_open`] is true. After `cooldown` one probe request
+/// is let through (half-open): the circuit closes if it succeeds and opens
+/// again if it fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitBreakerPolicy {
+    pub failure_threshold: u32,
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Availability of Casdoor as seen by the circuit breaker, see
+/// [`Sdk::health_state`](crate::Sdk::health_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HealthState {
+    /// Requests are sent normally.
+    Closed,
+    /// Requests fail fast until the cooldown has passed.
+    Open,
+    /// The cooldown has passed; the next request probes Casdoor.
+    HalfOpen,
+}
+
+impl HealthState {
+    pub const fn is_healthy(self) -> bool {
+        matches!(self, Self::Closed)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    policy: CircuitBreakerPolicy,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    opened_at: Option<Instant>,
+    /// When the probe of the half-open circuit was sent. A probe that never
+    /// finished, e.g. because its future was dropped, expires after the
+    /// cooldown.
+    probe_sent_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub(crate) const fn new(policy: CircuitBreakerPolicy) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(BreakerState {
+                failures: 0,
+                opened_at: None,
+                probe_sent_at: None,
+            }),
+        }
+    }
+
+    pub(crate) const fn policy(&self) -> &CircuitBreakerPolicy {
+        &self.policy
+    }
+
+    fn state(&self) -> MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn health_state(&self) -> HealthState {
+        self.health_state_at(Instant::now())
+    }
+
+    fn health_state_at(&self, now: Instant) -> HealthState {
+        let state = self.state();
+        match state.opened_at {
+            None => HealthState::Closed,
+            Some(opened_at) if now < opened_at + self.policy.cooldown => HealthState::Open,
+            Some(_) => HealthState::HalfOpen,
+        }
+    }
+
+    /// Fails fast if the circuit is open; lets one probe through when it is
+    /// half-open.
+    pub(crate) fn acquire(&self) -> SdkResult<()> {
+        self.acquire_at(Instant::now())
+    }
+
+    fn acquire_at(&self, now: Instant) -> SdkResult<()> {
+        let mut state = self.state();
+        let Some(opened_at) = state.opened_at else {
+            return Ok(());
+        };
+        let cooldown = self.policy.cooldown;
+        let probing = state.probe_sent_at.is_some_and(|sent_at| now < sent_at + cooldown);
+        if now < opened_at + cooldown || probing {
+            return Err(SdkError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                SdkInnerError::CircuitOpen,
+            ));
+        }
+        state.probe_sent_at = Some(now);
+        Ok(())
+    }
+
+    /// Records the outcome of a request let through by [`Self::acquire`].
+    pub(crate) fn record(&self, res: &SdkResult<super::HttpResponse>) {
+        let failed = match res {
+            Ok(res) => res.status.is_server_error(),
+            Err(e) => e.is_transport(),
+        };
+        self.record_at(Instant::now(), failed);
+    }
+
+    fn record_at(&self, now: Instant, failed: bool) {
+        let mut state = self.state();
+        if !failed {
+            *state = BreakerState::default();
+            return;
+        }
+        state.failures = state.failures.saturating_add(1);
+        if state.probe_sent_at.is_some() || state.failures >= self.policy.failure_threshold.max(1) {
+            state.opened_at = Some(now);
+            state.probe_sent_at = None;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(CircuitBreakerPolicy {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(10),
+        });
+        let now = Instant::now();
+        breaker.record_at(now, true);
+        breaker.record_at(now, false);
+        breaker.record_at(now, true);
+        assert_eq!(HealthState::Closed, breaker.health_state_at(now));
+        assert!(breaker.acquire_at(now).is_ok());
+        breaker.record_at(now, true);
+        assert_eq!(HealthState::Open, breaker.health_state_at(now));
+        assert!(breaker.acquire_at(now).unwrap_err().is_circuit_open());
+
+        // One probe after the cooldown; failing it opens the circuit again.
+        let now = now + Duration::from_secs(10);
+        assert_eq!(HealthState::HalfOpen, breaker.health_state_at(now));
+        assert!(breaker.acquire_at(now).is_ok());
+        assert!(breaker.acquire_at(now).is_err());
+        breaker.record_at(now, true);
+        assert_eq!(HealthState::Open, breaker.health_state_at(now));
+
+        // A successful probe closes it.
+        let now = now + Duration::from_secs(10);
+        assert!(breaker.acquire_at(now).is_ok());
+        breaker.record_at(now, false);
+        assert_eq!(HealthState::Closed, breaker.health_state_at(now));
+        assert!(breaker.acquire_at(now).is_ok());
+    }
+
+    #[test]
+    fn test_abandoned_probe() {
+        let breaker = CircuitBreaker::new(CircuitBreakerPolicy {
+            failure_threshold: 1,
+            cooldown: Duration::from_secs(10),
+        });
+        let now = Instant::now();
+        breaker.record_at(now, true);
+        let now = now + Duration::from_secs(10);
+        assert!(breaker.acquire_at(now).is_ok());
+        assert!(breaker.acquire_at(now + Duration::from_secs(5)).is_err());
+        assert!(breaker.acquire_at(now + Duration::from_secs(10)).is_ok());
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use reqwest::{
+    Certificate, Client, Proxy,
+    header::{HeaderMap, HeaderName, HeaderValue},
+};
+
+use super::{
+    CircuitBreaker, CircuitBreakerPolicy, ConfigState, FailoverPolicy, LimitPolicy, Limiter, Middleware,
+    ReqwestTransport, RetryPolicy, Sdk, SdkInner, Transport,
+};
+use crate::{
+    Config, ConfigError, DEFAULT_JWT_ALGORITHMS, DiscoveryCache, DiscoveryPolicy, JwksPolicy, JwksStore, JwtAlgorithm,
+    ModPath, ParsedCertificate, SdkResult,
+};
+
+/// Builder for a [`Sdk`] that owns a shared, reusable HTTP client.
+///
+/// All clones of the built [`Sdk`] share one connection pool, so keep-alive
+/// connections and TLS sessions are reused across calls.
+#[derive(Debug)]
+pub struct SdkBuilder {
+    config: Config,
+    transport: Option<Arc<dyn Transport>>,
+    client: Option<Client>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxies: Vec<Proxy>,
+    root_certificates: Vec<Certificate>,
+    default_headers: HeaderMap,
+    user_agent: Option<String>,
+    retry_policy: RetryPolicy,
+    limit_policy: LimitPolicy,
+    circuit_breaker: Option<CircuitBreakerPolicy>,
+    failover_policy: FailoverPolicy,
+    certificate_overlap: Duration,
+    jwt_algorithms: Vec<JwtAlgorithm>,
+    /// The first name passed to [`SdkBuilder::jwt_algorithm_names`] that is
+    /// not a [`JwtAlgorithm`], reported by [`SdkBuilder::build`].
+    unknown_jwt_algorithm: Option<String>,
+    jwks: Option<JwksPolicy>,
+    discovery_policy: DiscoveryPolicy,
+    error_mod_path: Option<ModPath>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl SdkBuilder {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            transport: None,
+            client: None,
+            connect_timeout: None,
+            read_timeout: None,
+            timeout: None,
+            proxies: Vec::new(),
+            root_certificates: Vec::new(),
+            default_headers: HeaderMap::new(),
+            user_agent: None,
+            retry_policy: RetryPolicy::default(),
+            limit_policy: LimitPolicy::default(),
+            circuit_breaker: None,
+            failover_policy: FailoverPolicy::default(),
+            certificate_overlap: Duration::ZERO,
+            jwt_algorithms: DEFAULT_JWT_ALGORITHMS.to_vec(),
+            unknown_jwt_algorithm: None,
+            jwks: None,
+            discovery_policy: DiscoveryPolicy::default(),
+            error_mod_path: None,
+            middlewares: Vec::new(),
+        }
+    }
+
+    /// Send all requests through the given transport, e.g. a
+    /// [`MockTransport`](super::MockTransport) in tests. When set, all HTTP
+    /// options of this builder are ignored.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Use a pre-built client. When set, all other HTTP options of this
+    /// builder are ignored.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Timeout for establishing a connection.
+    pub const fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for each read operation on the response body.
+    pub const fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Total timeout of a request, from connecting until the body is read.
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Route requests through the given proxy. Can be called multiple times.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Trust an extra root certificate, e.g. a private CA of the Casdoor
+    /// server.
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Send the header with every request.
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    /// Send all of the headers with every request.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers.extend(headers);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Retry failed requests, see [`RetryPolicy`]. Idempotent requests are
+    /// retried with [`RetryPolicy::default`] unless set to
+    /// [`RetryPolicy::none`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Limit the request rate and concurrency, see [`LimitPolicy`].
+    pub const fn limit_policy(mut self, policy: LimitPolicy) -> Self {
+        self.limit_policy = policy;
+        self
+    }
+
+    /// Fail fast while Casdoor is unavailable, see [`CircuitBreakerPolicy`].
+    /// Disabled by default.
+    pub const fn circuit_breaker(mut self, policy: CircuitBreakerPolicy) -> Self {
+        self.circuit_breaker = Some(policy);
+        self
+    }
+
+    /// How requests fail over between the endpoints of the [`Config`], see
+    /// [`FailoverPolicy`].
+    pub fn failover_policy(mut self, policy: FailoverPolicy) -> Self {
+        self.failover_policy = policy;
+        self
+    }
+
+    /// Keep accepting JWTs signed with a certificate replaced by
+    /// [`Sdk::reload`] for the given time, so tokens issued before a
+    /// rotation stay valid. Zero by default.
+    pub const fn certificate_overlap(mut self, overlap: Duration) -> Self {
+        self.certificate_overlap = overlap;
+        self
+    }
+
+    /// The algorithms JWTs may be signed with, matching the
+    /// `token_signing_method` of the application. Defaults to
+    /// [`DEFAULT_JWT_ALGORITHMS`]; HS256, HS384 and HS512 verify with the
+    /// client secret.
+    pub fn jwt_algorithms(mut self, algorithms: impl IntoIterator<Item = JwtAlgorithm>) -> Self {
+        self.jwt_algorithms = algorithms.into_iter().collect();
+        self.unknown_jwt_algorithm = None;
+        self
+    }
+
+    /// Like [`SdkBuilder::jwt_algorithms`], with names as in the
+    /// `token_signing_method` of the application, e.g. `RS256`.
+    /// [`SdkBuilder::build`] fails for names that are not a
+    /// [`JwtAlgorithm`], such as `EdDSA`.
+    pub fn jwt_algorithm_names(mut self, names: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.jwt_algorithms.clear();
+        self.unknown_jwt_algorithm = None;
+        for name in names {
+            match name.as_ref().parse() {
+                Ok(algorithm) => self.jwt_algorithms.push(algorithm),
+                Err(_) => {
+                    self.unknown_jwt_algorithm
+                        .get_or_insert_with(|| name.as_ref().to_owned());
+                }
+            }
+        }
+        self
+    }
+
+    /// Verify JWTs with the keys Casdoor publishes at its JWKS endpoint,
+    /// selected by the `kid` of the token, falling back to the certificate
+    /// of the [`Config`]. See [`JwksPolicy`]. Disabled by default.
+    pub fn jwks(mut self, policy: JwksPolicy) -> Self {
+        self.jwks = Some(policy);
+        self
+    }
+
+    /// How the OpenID Connect discovery document is cached and used, see
+    /// [`DiscoveryPolicy`].
+    pub fn discovery_policy(mut self, policy: DiscoveryPolicy) -> Self {
+        self.discovery_policy = policy;
+        self
+    }
+
+    /// Suffix of the error codes of
+    /// [`SdkError::into_api_error`](crate::SdkError::into_api_error).
+    /// Defaults to the
+    /// [`default_error_mod_path`](crate::default_error_mod_path).
+    pub const fn error_mod_path(mut self, mod_path: ModPath) -> Self {
+        self.error_mod_path = Some(mod_path);
+        self
+    }
+
+    /// Append a middleware to the chain, see [`Middleware`].
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Builds the Sdk, failing if the certificate of the config cannot be
+    /// parsed or a JWT algorithm is not supported.
+    pub fn build(mut self) -> SdkResult<Sdk> {
+        if !self.config.certificate().is_empty() {
+            ParsedCertificate::parse(self.config.certificate())?;
+        }
+        if let Some(name) = self.unknown_jwt_algorithm.take() {
+            return Err(ConfigError::UnsupportedJwtAlgorithm(name).into());
+        }
+        let transport: Arc<dyn Transport> = match (self.transport.take(), self.client.take()) {
+            (Some(transport), _) => transport,
+            (None, Some(client)) => Arc::new(ReqwestTransport::new(client)),
+            (None, None) => {
+                let mut builder = Client::builder().default_headers(std::mem::take(&mut self.default_headers));
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(timeout) = self.read_timeout {
+                    builder = builder.read_timeout(timeout);
+                }
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                for proxy in self.proxies.drain(..) {
+                    builder = builder.proxy(proxy);
+                }
+                for certificate in self.root_certificates.drain(..) {
+                    builder = builder.add_root_certificate(certificate);
+                }
+                if let Some(user_agent) = self.user_agent.take() {
+                    builder = builder.user_agent(user_agent);
+                }
+                Arc::new(ReqwestTransport::new(builder.build()?))
+            }
+        };
+        Ok(self.finish(transport))
+    }
+
+    pub(crate) fn finish(self, transport: Arc<dyn Transport>) -> Sdk {
+        Sdk {
+            inner: Arc::new(SdkInner {
+                state: RwLock::new(ConfigState::new(self.config, self.failover_policy.clone())),
+                transport,
+                retry_policy: self.retry_policy,
+                limiter: Limiter::new(self.limit_policy),
+                failover_policy: self.failover_policy,
+                certificate_overlap: self.certificate_overlap,
+                circuit_breaker: self.circuit_breaker.map(CircuitBreaker::new),
+                jwt_algorithms: self.jwt_algorithms,
+                jwks: self.jwks.map(JwksStore::new),
+                discovery: DiscoveryCache::new(self.discovery_policy),
+                error_mod_path: self.error_mod_path,
+                middlewares: self.middlewares,
+            }),
+            credentials: None,
+            scope: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn sdk_builder(self) -> SdkBuilder {
+        SdkBuilder::new(self)
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use super::HttpRequest;
+use crate::Config;
+
+/// Controls how requests fail over between the endpoints of the
+/// [`Config`], see [`Config::with_fallback_endpoints`].
+///
+/// Requests go to the first healthy endpoint in order. An endpoint that
+/// fails with a transport error is skipped for `cooldown`, after which it is
+/// tried again by the next request, or earlier by
+/// [`Sdk::probe_endpoints`](crate::Sdk::probe_endpoints).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailoverPolicy {
+    pub cooldown: Duration,
+    /// Path requested by [`Sdk::probe_endpoints`](crate::Sdk::probe_endpoints).
+    pub probe_path: String,
+    /// Whether an idempotent request may try every endpoint once, even if
+    /// the [`RetryPolicy`](crate::RetryPolicy) allows fewer attempts, e.g.
+    /// [`RetryPolicy::none`](crate::RetryPolicy::none). Set it to `false` to
+    /// bound the attempts by the retry policy alone.
+    pub try_every_endpoint: bool,
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self {
+            cooldown: Duration::from_secs(30),
+            probe_path: "/api/health".to_owned(),
+            try_every_endpoint: true,
+        }
+    }
+}
+
+/// Health of one endpoint, see
+/// [`Sdk::endpoint_health`](crate::Sdk::endpoint_health).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointHealth {
+    pub endpoint: String,
+    pub healthy: bool,
+}
+
+/// The endpoints of a [`Sdk`](crate::Sdk) and their health.
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    policy: FailoverPolicy,
+    urls: Vec<String>,
+    /// Until when each endpoint is skipped.
+    unhealthy_until: Mutex<Vec<Option<Instant>>>,
+}
+
+impl Endpoints {
+    pub(crate) fn new(config: &Config, policy: FailoverPolicy) -> Self {
+        let urls: Vec<String> = config.endpoints().cloned().collect();
+        Self {
+            policy,
+            unhealthy_until: Mutex::new(vec![None; urls.len()]),
+            urls,
+        }
+    }
+
+    pub(crate) const fn policy(&self) -> &FailoverPolicy {
+        &self.policy
+    }
+
+    pub(crate) const fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    fn state(&self) -> MutexGuard<'_, Vec<Option<Instant>>> {
+        self.unhealthy_until.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The index of the endpoint for the next request: the first healthy one,
+    /// or the one that recovers first if none is healthy.
+    pub(crate) fn select(&self) -> usize {
+        self.select_at(Instant::now())
+    }
+
+    fn select_at(&self, now: Instant) -> usize {
+        let state = self.state();
+        state
+            .iter()
+            .position(|until| until.is_none_or(|until| until <= now))
+            .or_else(|| {
+                state
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, until)| **until)
+                    .map(|(index, _)| index)
+            })
+            .unwrap_or_default()
+    }
+
+    /// The rest of the URL if it points at the primary endpoint, e.g. not
+    /// at a URL of the discovery document.
+    fn path_of<'a>(&self, url: &'a str) -> Option<&'a str> {
+        let path = url.strip_prefix(self.urls.first()?.as_str())?;
+        matches!(path.chars().next(), None | Some('/' | '?')).then_some(path)
+    }
+
+    /// Whether the URL points at the primary endpoint, so the request can
+    /// fail over.
+    pub(crate) fn routes(&self, url: &str) -> bool {
+        self.path_of(url).is_some()
+    }
+
+    /// Points the request, built against the primary endpoint, at the
+    /// endpoint with the index.
+    pub(crate) fn route(&self, req: &HttpRequest, index: usize) -> HttpRequest {
+        let mut req = req.clone();
+        if let (Some(path), Some(endpoint)) = (self.path_of(&req.url), self.urls.get(index)) {
+            if index > 0 {
+                req.url = format!("{endpoint}{path}");
+            }
+        }
+        req
+    }
+
+    pub(crate) fn mark(&self, index: usize, healthy: bool) {
+        self.mark_at(index, healthy, Instant::now());
+    }
+
+    fn mark_at(&self, index: usize, healthy: bool, now: Instant) {
+        if let Some(until) = self.state().get_mut(index) {
+            *until = (!healthy).then(|| now + self.policy.cooldown);
+        }
+    }
+
+    pub(crate) fn health(&self) -> Vec<EndpointHealth> {
+        let now = Instant::now();
+        self.urls
+            .iter()
+            .zip(self.state().iter())
+            .map(|(endpoint, until)| EndpointHealth {
+                endpoint: endpoint.clone(),
+                healthy: until.is_none_or(|until| until <= now),
+            })
+            .collect()
+    }
+
+    pub(crate) fn urls(&self) -> &[String] {
+        &self.urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+
+    #[test]
+    fn test_endpoints() {
+        let config = Config::new("http://a.test", "cid", "secret", "", "built-in", None)
+            .with_fallback_endpoints(["http://b.test", "http://c.test"]);
+        let endpoints = Endpoints::new(&config, FailoverPolicy::default());
+        let now = Instant::now();
+        assert_eq!(0, endpoints.select_at(now));
+        endpoints.mark_at(0, false, now);
+        assert_eq!(1, endpoints.select_at(now));
+        endpoints.mark_at(1, false, now + Duration::from_secs(1));
+        assert_eq!(2, endpoints.select_at(now));
+        endpoints.mark_at(2, false, now + Duration::from_secs(2));
+        assert_eq!(0, endpoints.select_at(now));
+        assert_eq!(0, endpoints.select_at(now + Duration::from_secs(30)));
+        endpoints.mark_at(0, false, now + Duration::from_secs(30));
+        assert_eq!(1, endpoints.select_at(now + Duration::from_secs(31)));
+        endpoints.mark_at(0, true, now);
+        assert_eq!(0, endpoints.select_at(now));
+
+        let req = HttpRequest {
+            method: Method::GET,
+            url: "http://a.test/api/get-users?owner=built-in".to_owned(),
+            headers: Default::default(),
+            body: Vec::new(),
+        };
+        assert_eq!(req.url, endpoints.route(&req, 0).url);
+        assert_eq!(
+            "http://c.test/api/get-users?owner=built-in",
+            endpoints.route(&req, 2).url
+        );
+        let foreign = HttpRequest {
+            url: "http://a.test.evil/api/get-users".to_owned(),
+            ..req
+        };
+        assert!(!endpoints.routes(&foreign.url));
+        assert_eq!(foreign.url, endpoints.route(&foreign, 2).url);
+        assert!(endpoints.routes("http://a.test"));
+        assert!(endpoints.routes("http://a.test?owner=built-in"));
+    }
+}
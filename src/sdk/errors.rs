@@ -1,15 +1,23 @@
-use std::{fmt::Display, sync::Once};
+use std::{fmt::Display, sync::OnceLock};
 
-pub use cubix::api_response::ApiError;
-use cubix::api_response::error_code::{ErrType, ModPath, ModSection};
+use cubix::api_response::error_code::ErrType;
+pub use cubix::api_response::{
+    ApiError,
+    error_code::{ModPath, ModSection, ModSegment},
+};
 
-use crate::StatusCode;
+use crate::{
+    ConfigError, HttpRequest, HttpResponse, Method, OperationClass, StatusCode, TokenValidationError,
+    header::CONTENT_TYPE,
+};
 
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct SdkError {
     pub code: StatusCode,
     pub inner: SdkInnerError,
+    attempts: u32,
+    context: Option<Box<RequestContext>>,
 }
 
 impl SdkError {
@@ -17,45 +25,345 @@ impl SdkError {
         Self {
             code,
             inner: inner.into(),
+            attempts: 1,
+            context: None,
+        }
+    }
+
+    /// The request that failed, if the error came from one.
+    pub fn context(&self) -> Option<&RequestContext> {
+        self.context.as_deref()
+    }
+
+    /// Attaches the context unless the error already has one.
+    pub(crate) fn with_context(mut self, context: Option<RequestContext>) -> Self {
+        if self.context.is_none() {
+            self.context = context.map(Box::new);
+        }
+        self
+    }
+
+    /// Number of times the request was sent before giving up.
+    pub const fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub(crate) const fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    /// Error reported by Casdoor in a `status: "error"` response. Its
+    /// [`kind`](Self::kind) is derived from the message, see
+    /// [`ErrorKind::from_casdoor_msg`].
+    pub fn casdoor(msg: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SdkInnerError::CasdoorError(msg.into()),
+        )
+    }
+
+    /// What went wrong, derived from the error and its status code.
+    pub fn kind(&self) -> ErrorKind {
+        match &self.inner {
+            SdkInnerError::RateLimited(_) => return ErrorKind::RateLimited,
+            SdkInnerError::CircuitOpen => return ErrorKind::CircuitOpen,
+            SdkInnerError::JwtError(_) => return ErrorKind::Unauthorized,
+            SdkInnerError::CasdoorError(msg) => return ErrorKind::from_casdoor_msg(msg).unwrap_or(ErrorKind::Casdoor),
+            _ => {}
+        }
+        if self.is_timeout() {
+            return ErrorKind::Timeout;
+        }
+        if self.is_transport() {
+            return ErrorKind::Transport;
+        }
+        if self.is_decode() {
+            return ErrorKind::Decode;
+        }
+        match self.code {
+            StatusCode::NOT_FOUND => ErrorKind::NotFound,
+            StatusCode::UNAUTHORIZED => ErrorKind::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorKind::Forbidden,
+            StatusCode::CONFLICT => ErrorKind::Conflict,
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => ErrorKind::Validation,
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => ErrorKind::Timeout,
+            StatusCode::TOO_MANY_REQUESTS => ErrorKind::RateLimited,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Whether the request failed to reach Casdoor or to get a complete
+    /// response back, as opposed to being rejected by Casdoor.
+    pub fn is_transport(&self) -> bool {
+        match &self.inner {
+            SdkInnerError::ReqwestError(e) => e.is_connect() || e.is_timeout() || e.is_request() || e.is_body(),
+            SdkInnerError::TransportError(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(&self.inner, SdkInnerError::ReqwestError(e) if e.is_timeout())
+    }
+
+    /// Whether the request was not sent because it would have waited longer
+    /// than [`LimitPolicy::max_wait`](crate::LimitPolicy::max_wait).
+    pub const fn is_rate_limited(&self) -> bool {
+        matches!(self.inner, SdkInnerError::RateLimited(_))
+    }
+
+    /// Whether the request was not sent because the circuit breaker is open,
+    /// see [`CircuitBreakerPolicy`](crate::CircuitBreakerPolicy).
+    pub const fn is_circuit_open(&self) -> bool {
+        matches!(self.inner, SdkInnerError::CircuitOpen)
+    }
+
+    /// Whether the response was not a Casdoor API response, see
+    /// [`SdkInnerError::UnexpectedResponse`].
+    pub const fn is_unexpected_response(&self) -> bool {
+        matches!(self.inner, SdkInnerError::UnexpectedResponse { .. })
+    }
+
+    pub(crate) fn unexpected_response(res: &HttpResponse) -> Self {
+        Self::new(
+            if res.status.is_client_error() || res.status.is_server_error() {
+                res.status
+            } else {
+                StatusCode::BAD_GATEWAY
+            },
+            SdkInnerError::UnexpectedResponse {
+                status: res.status,
+                content_type: res
+                    .headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_owned),
+                snippet: res.body_snippet().into_owned(),
+            },
+        )
+    }
+
+    /// Whether the response body could not be decoded.
+    pub fn is_decode(&self) -> bool {
+        match &self.inner {
+            SdkInnerError::UnexpectedResponse { .. } => true,
+            SdkInnerError::ReqwestError(e) => e.is_decode(),
+            SdkInnerError::SerdeJsonError(e) => !e.is_io(),
+            _ => false,
         }
     }
 }
 
 impl Display for SdkError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.inner)
+        write!(f, "{}", self.inner)?;
+        if self.attempts > 1 {
+            write!(f, " (after {} attempts)", self.attempts)?;
+        }
+        if let Some(context) = &self.context {
+            write!(f, " [{context}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Diagnostic context of a failed request, see [`SdkError::context`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RequestContext {
+    pub method: Method,
+    /// Path and query of the request, with secrets redacted.
+    pub path: String,
+    /// The operation name used in metrics and traces.
+    pub operation: String,
+    /// [`Model::ident`](crate::Model::ident) of model operations.
+    pub model: Option<&'static str>,
+    /// The `name` field of the Casdoor response, if any.
+    pub name: Option<String>,
+    /// The `sub` field of the Casdoor response, if any.
+    pub sub: Option<String>,
+}
+
+impl RequestContext {
+    pub(crate) fn new(req: &HttpRequest, operation: &str, model: Option<&'static str>) -> Self {
+        Self {
+            method: req.method.clone(),
+            path: crate::utils::redact_url(&req.path_and_query()),
+            operation: operation.to_owned(),
+            model,
+            name: None,
+            sub: None,
+        }
+    }
+}
+
+impl Display for RequestContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.path)?;
+        if let Some(model) = self.model {
+            write!(f, ", model: {model}")?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, ", name: {name}")?;
+        }
+        if let Some(sub) = &self.sub {
+            write!(f, ", sub: {sub}")?;
+        }
+        Ok(())
     }
 }
 
-impl std::error::Error for SdkError {}
+impl std::error::Error for SdkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner.source()
+    }
+}
+
+/// The category of a [`SdkError`], see [`SdkError::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    NotFound,
+    /// Missing or invalid credentials or tokens.
+    Unauthorized,
+    /// The credentials are valid but not allowed to do this.
+    Forbidden,
+    /// The object already exists.
+    Conflict,
+    /// The request was rejected as invalid.
+    Validation,
+    Transport,
+    Timeout,
+    /// The response could not be decoded.
+    Decode,
+    RateLimited,
+    CircuitOpen,
+    /// An error reported by Casdoor that is not known to the SDK.
+    Casdoor,
+    Other,
+}
+
+/// Known Casdoor messages, matched case-insensitively as substrings in order.
+const CASDOOR_MESSAGES: &[(&str, ErrorKind)] = &[
+    ("invalid client secret", ErrorKind::Unauthorized),
+    ("invalid client_id", ErrorKind::Unauthorized),
+    ("unauthorized operation", ErrorKind::Unauthorized),
+    ("please login first", ErrorKind::Unauthorized),
+    ("please sign in first", ErrorKind::Unauthorized),
+    ("token is expired", ErrorKind::Unauthorized),
+    ("token has expired", ErrorKind::Unauthorized),
+    ("invalid token", ErrorKind::Unauthorized),
+    ("invalid access token", ErrorKind::Unauthorized),
+    ("don't have the permission", ErrorKind::Forbidden),
+    ("do not have the permission", ErrorKind::Forbidden),
+    ("permission denied", ErrorKind::Forbidden),
+    ("is forbidden", ErrorKind::Forbidden),
+    ("doesn't exist", ErrorKind::NotFound),
+    ("does not exist", ErrorKind::NotFound),
+    ("already exist", ErrorKind::Conflict),
+    ("already used", ErrorKind::Conflict),
+    ("has been used", ErrorKind::Conflict),
+    ("duplicate", ErrorKind::Conflict),
+    ("invalid phone", ErrorKind::Validation),
+    ("invalid email", ErrorKind::Validation),
+    ("cannot be empty", ErrorKind::Validation),
+    ("can not be empty", ErrorKind::Validation),
+    ("is empty", ErrorKind::Validation),
+    ("missing parameter", ErrorKind::Validation),
+];
+
+impl ErrorKind {
+    /// Classifies a Casdoor error message, such as `The user: built-in/alice
+    /// doesn't exist`.
+    pub fn from_casdoor_msg(msg: &str) -> Option<Self> {
+        let msg = msg.to_lowercase();
+        CASDOOR_MESSAGES
+            .iter()
+            .find(|(pattern, _)| msg.contains(pattern))
+            .map(|(_, kind)| *kind)
+    }
+}
 
 #[derive(Debug)]
 pub enum SdkInnerError {
     StringError(String),
+    /// The `msg` of a Casdoor error response.
+    CasdoorError(String),
     ReqwestError(reqwest::Error),
+    TransportError(String),
+    SerdeJsonError(serde_json::Error),
     SerdeUrlencodedSerError(serde_urlencoded::ser::Error),
     Oauth2UrlParseError(oauth2::url::ParseError),
     Oauth2RequestTokenError(String),
     JwtError(jsonwebtoken::errors::Error),
+    /// The config is invalid, e.g. its certificate cannot be parsed.
+    ConfigError(ConfigError),
+    /// A JWT with a valid signature is rejected by the
+    /// [`TokenValidationPolicy`](crate::TokenValidationPolicy).
+    TokenValidation(TokenValidationError),
+    RateLimited(OperationClass),
+    CircuitOpen,
+    /// A response that is not a Casdoor API response, e.g. the HTML error
+    /// page of a proxy.
+    UnexpectedResponse {
+        status: StatusCode,
+        content_type: Option<String>,
+        /// The start of the body, see [`HttpResponse::body_snippet`].
+        snippet: String,
+    },
 }
 
 impl Display for SdkInnerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SdkInnerError::StringError(error) => write!(f, "{error}"),
+            SdkInnerError::CasdoorError(msg) => write!(f, "{msg}"),
             SdkInnerError::ReqwestError(error) => write!(f, "{error}"),
+            SdkInnerError::TransportError(error) => write!(f, "{error}"),
+            SdkInnerError::SerdeJsonError(error) => write!(f, "{error}"),
             SdkInnerError::SerdeUrlencodedSerError(error) => write!(f, "{error}"),
             SdkInnerError::Oauth2UrlParseError(error) => write!(f, "{error}"),
             SdkInnerError::Oauth2RequestTokenError(error) => write!(f, "{error}"),
             SdkInnerError::JwtError(error) => write!(f, "{error}"),
+            SdkInnerError::ConfigError(error) => write!(f, "{error}"),
+            SdkInnerError::TokenValidation(error) => write!(f, "{error}"),
+            SdkInnerError::RateLimited(class) => write!(f, "client-side {class} limit exceeded the max wait"),
+            SdkInnerError::CircuitOpen => write!(f, "circuit breaker is open, Casdoor is considered unavailable"),
+            SdkInnerError::UnexpectedResponse {
+                status,
+                content_type,
+                snippet,
+            } => write!(
+                f,
+                "unexpected {status} response ({}): {snippet}",
+                content_type.as_deref().unwrap_or("no content type")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SdkInnerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SdkInnerError::ReqwestError(error) => Some(error),
+            SdkInnerError::SerdeJsonError(error) => Some(error),
+            SdkInnerError::SerdeUrlencodedSerError(error) => Some(error),
+            SdkInnerError::Oauth2UrlParseError(error) => Some(error),
+            SdkInnerError::JwtError(error) => Some(error),
+            SdkInnerError::ConfigError(error) => Some(error),
+            SdkInnerError::TokenValidation(error) => Some(error),
+            _ => None,
         }
     }
 }
 
+/// The URL is dropped from the error, as its query may hold secrets; the
+/// [`RequestContext`] keeps the redacted path.
 impl From<reqwest::Error> for SdkError {
     fn from(e: reqwest::Error) -> Self {
-        Self {
-            code: if let Some(status) = e.status() {
+        Self::new(
+            if let Some(status) = e.status() {
                 status
             } else if e.is_timeout() {
                 StatusCode::REQUEST_TIMEOUT
@@ -64,8 +372,8 @@ impl From<reqwest::Error> for SdkError {
             } else {
                 StatusCode::INTERNAL_SERVER_ERROR
             },
-            inner: SdkInnerError::ReqwestError(e),
-        }
+            SdkInnerError::ReqwestError(e.without_url()),
+        )
     }
 }
 
@@ -81,6 +389,12 @@ impl From<serde_urlencoded::ser::Error> for SdkError {
     }
 }
 
+impl From<serde_json::Error> for SdkError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, SdkInnerError::SerdeJsonError(value))
+    }
+}
+
 impl From<oauth2::url::ParseError> for SdkError {
     fn from(value: oauth2::url::ParseError) -> Self {
         Self::new(StatusCode::BAD_REQUEST, SdkInnerError::Oauth2UrlParseError(value))
@@ -93,6 +407,18 @@ impl From<jsonwebtoken::errors::Error> for SdkError {
     }
 }
 
+impl From<ConfigError> for SdkError {
+    fn from(value: ConfigError) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, SdkInnerError::ConfigError(value))
+    }
+}
+
+impl From<TokenValidationError> for SdkError {
+    fn from(value: TokenValidationError) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, SdkInnerError::TokenValidation(value))
+    }
+}
+
 impl<RE, TE> From<oauth2::RequestTokenError<RE, TE>> for SdkError
 where
     RE: std::error::Error + 'static,
@@ -116,7 +442,7 @@ impl SdkError {
         use salvo::prelude::StatusError;
         StatusError::from_code(self.code)
             .unwrap_or(StatusError::internal_server_error())
-            .brief(self.inner.to_string())
+            .brief(self.to_string())
     }
 }
 
@@ -127,21 +453,119 @@ impl From<SdkError> for salvo::prelude::StatusError {
     }
 }
 
-static mut MOD_PATH: ModPath = ModPath::default();
+static DEFAULT_MOD_PATH: OnceLock<ModPath> = OnceLock::new();
 
-static INIT: Once = Once::new();
-
-/// Initialize the suffix of the error code.
+/// Initialize the process-wide default suffix of the error code, used by Sdks
+/// without their own
+/// [`SdkBuilder::error_mod_path`](crate::SdkBuilder::error_mod_path).
+/// Only the first call has an effect.
 pub fn init_error_mod_path(mod1: ModSection, mod2: ModSection, mod3: ModSection) {
-    unsafe {
-        INIT.call_once(|| MOD_PATH = ModPath::new(mod1, mod2, mod3));
-    }
+    let _ = DEFAULT_MOD_PATH.set(ModPath::new(mod1, mod2, mod3));
 }
+
+/// The process-wide default suffix of the error code, see
+/// [`init_error_mod_path`].
+pub fn default_error_mod_path() -> ModPath {
+    DEFAULT_MOD_PATH.get().copied().unwrap_or_else(ModPath::default)
+}
+
 pub const ERR_SDK_KEY: &str = "casdoor_sdk_err";
+
+impl SdkError {
+    /// Converts into an [`ApiError`] with the error code suffix of the Sdk.
+    pub fn into_api_error(self, sdk: &crate::Sdk) -> ApiError {
+        self.into_api_error_with(sdk.error_mod_path())
+    }
+
+    pub fn into_api_error_with(self, mod_path: ModPath) -> ApiError {
+        ErrType::from(self.code)
+            .new_api_error(mod_path)
+            .with_detail(ERR_SDK_KEY, self.to_string())
+    }
+}
+
+/// Uses the [`default_error_mod_path`]; see [`SdkError::into_api_error`] for
+/// the one of a specific Sdk.
 impl From<SdkError> for ApiError {
     fn from(value: SdkError) -> Self {
-        ErrType::from(value.code)
-            .new_api_error(unsafe { MOD_PATH })
-            .with_detail(ERR_SDK_KEY, value.to_string())
+        value.into_api_error_with(default_error_mod_path())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+
+    #[test]
+    fn test_casdoor_error_kind() {
+        for (msg, kind) in [
+            ("The user: built-in/alice doesn't exist", ErrorKind::NotFound),
+            ("Unauthorized operation", ErrorKind::Unauthorized),
+            ("Invalid client secret", ErrorKind::Unauthorized),
+            ("The user name is already used", ErrorKind::Conflict),
+            (
+                "Duplicate entry 'built-in-alice' for key 'PRIMARY'",
+                ErrorKind::Conflict,
+            ),
+            ("Invalid phone number", ErrorKind::Validation),
+            ("You don't have the permission to do this", ErrorKind::Forbidden),
+            ("something unexpected happened", ErrorKind::Casdoor),
+            ("Invalid application or wrong clientSecret", ErrorKind::Casdoor),
+            ("Model not found for the permission", ErrorKind::Casdoor),
+        ] {
+            let err = SdkError::casdoor(msg);
+            assert_eq!(kind, err.kind(), "{msg}");
+            assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, err.code, "{msg}");
+            assert_eq!(msg, err.to_string());
+        }
+        assert_eq!(
+            ErrorKind::Other,
+            SdkError::new(StatusCode::INTERNAL_SERVER_ERROR, "boom").kind()
+        );
+        assert_eq!(
+            ErrorKind::Transport,
+            SdkError::new(
+                StatusCode::BAD_GATEWAY,
+                SdkInnerError::TransportError("refused".to_owned())
+            )
+            .kind()
+        );
+    }
+
+    #[test]
+    fn test_into_api_error() {
+        let tenant = |segment: ModSegment| {
+            let section = ModSection::new(segment, "tenant");
+            ModPath::new(section, section, section)
+        };
+        let (sdk_a, _) = crate::test_support::mock_sdk_with(|builder| builder.error_mod_path(tenant(ModSegment::M01)));
+        let (sdk_b, _) = crate::test_support::mock_sdk_with(|builder| builder.error_mod_path(tenant(ModSegment::M02)));
+        let code_a = SdkError::casdoor("boom").into_api_error(&sdk_a).code();
+        let code_b = SdkError::casdoor("boom").into_api_error(&sdk_b).code();
+        assert_ne!(code_a, code_b);
+        assert_eq!(
+            code_a,
+            SdkError::casdoor("boom")
+                .into_api_error_with(tenant(ModSegment::M01))
+                .code()
+        );
+
+        let (sdk, _) = crate::test_support::mock_sdk();
+        assert_eq!(default_error_mod_path(), sdk.error_mod_path());
+        assert_eq!(
+            ApiError::from(SdkError::casdoor("boom")).code(),
+            SdkError::casdoor("boom").into_api_error(&sdk).code()
+        );
+    }
+
+    #[test]
+    fn test_error_source() {
+        let err = SdkError::from(serde_json::from_str::<u32>("x").unwrap_err());
+        assert_eq!(ErrorKind::Decode, err.kind());
+        assert!(err.source().unwrap().is::<serde_json::Error>());
+        assert!(SdkError::casdoor("boom").source().is_none());
     }
 }
@@ -0,0 +1,256 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{Method, SdkError, SdkInnerError, SdkResult, StatusCode};
+
+/// The budget a request is charged against, see [`LimitPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperationClass {
+    /// Requests without side effects, such as `get_users`.
+    Read,
+    /// Requests that change data, such as `update_model`.
+    Write,
+    /// Permission checks, such as `enforce` and `batch_enforce`.
+    Authz,
+}
+
+impl OperationClass {
+    /// GET, HEAD and OPTIONS requests are reads, everything else a write.
+    pub const fn of(method: &Method) -> Self {
+        if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+            Self::Read
+        } else {
+            Self::Write
+        }
+    }
+}
+
+impl Display for OperationClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Authz => "authz",
+        })
+    }
+}
+
+/// A token bucket allowing `requests` per `per`, with bursts of up to
+/// `requests`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    pub const fn new(requests: u32, per: Duration) -> Self {
+        Self { requests, per }
+    }
+
+    pub const fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+}
+
+/// Limits of one [`OperationClass`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub rate: Option<RateLimit>,
+    /// Maximum number of requests in flight at once.
+    pub max_in_flight: Option<usize>,
+}
+
+impl Budget {
+    pub const fn rate(mut self, rate: RateLimit) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    pub const fn max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+}
+
+/// Client-side limits, shared by all clones of a [`Sdk`](crate::Sdk).
+///
+/// Each attempt of a request waits until its class has budget left. When
+/// `max_wait` is set and a request would wait longer, it fails with an error
+/// for which [`SdkError::is_rate_limited`] is true. Nothing is limited by
+/// default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LimitPolicy {
+    pub read: Budget,
+    pub write: Budget,
+    pub authz: Budget,
+    pub max_wait: Option<Duration>,
+}
+
+impl LimitPolicy {
+    pub const fn budget(&self, class: OperationClass) -> &Budget {
+        match class {
+            OperationClass::Read => &self.read,
+            OperationClass::Write => &self.write,
+            OperationClass::Authz => &self.authz,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    policy: LimitPolicy,
+    read: ClassLimiter,
+    write: ClassLimiter,
+    authz: ClassLimiter,
+}
+
+#[derive(Debug)]
+struct ClassLimiter {
+    bucket: Option<TokenBucket>,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+/// A token bucket implemented as a generic cell rate algorithm: `tat` is the
+/// theoretical arrival time of the next request.
+#[derive(Debug)]
+struct TokenBucket {
+    interval: Duration,
+    tolerance: Duration,
+    tat: Mutex<Option<Instant>>,
+}
+
+/// Held while a request is in flight.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Limiter {
+    pub(crate) fn new(policy: LimitPolicy) -> Self {
+        Self {
+            read: ClassLimiter::new(&policy.read),
+            write: ClassLimiter::new(&policy.write),
+            authz: ClassLimiter::new(&policy.authz),
+            policy,
+        }
+    }
+
+    pub(crate) const fn policy(&self) -> &LimitPolicy {
+        &self.policy
+    }
+
+    /// Waits for budget of the class to send one request.
+    pub(crate) async fn acquire(&self, class: OperationClass) -> SdkResult<Permit> {
+        let limiter = match class {
+            OperationClass::Read => &self.read,
+            OperationClass::Write => &self.write,
+            OperationClass::Authz => &self.authz,
+        };
+        let deadline = self.policy.max_wait.map(|max_wait| Instant::now() + max_wait);
+        let permit = match &limiter.semaphore {
+            Some(semaphore) => {
+                let acquire = semaphore.clone().acquire_owned();
+                let permit = match deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline.into(), acquire)
+                        .await
+                        .map_err(|_| rate_limited(class))?,
+                    None => acquire.await,
+                };
+                Some(permit.map_err(|_| rate_limited(class))?)
+            }
+            None => None,
+        };
+        if let Some(bucket) = &limiter.bucket {
+            let wait = bucket
+                .reserve(Instant::now(), deadline)
+                .ok_or_else(|| rate_limited(class))?;
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+        Ok(Permit { _permit: permit })
+    }
+}
+
+impl ClassLimiter {
+    fn new(budget: &Budget) -> Self {
+        Self {
+            bucket: budget.rate.map(TokenBucket::new),
+            semaphore: budget
+                .max_in_flight
+                .map(|max_in_flight| Arc::new(Semaphore::new(max_in_flight))),
+        }
+    }
+}
+
+impl TokenBucket {
+    fn new(rate: RateLimit) -> Self {
+        let interval = rate.per / rate.requests.max(1);
+        Self {
+            interval,
+            tolerance: rate.per.saturating_sub(interval),
+            tat: Mutex::new(None),
+        }
+    }
+
+    /// Takes a token and returns how long to wait until it may be used, or
+    /// `None` without taking it if it is not available now and the wait
+    /// would end after the deadline.
+    fn reserve(&self, now: Instant, deadline: Option<Instant>) -> Option<Duration> {
+        let mut tat = self.tat.lock().unwrap_or_else(PoisonError::into_inner);
+        let start = tat.map_or(now, |tat| tat.max(now));
+        let ready_at = start
+            .checked_sub(self.tolerance)
+            .map_or(now, |ready_at| ready_at.max(now));
+        if ready_at > now && deadline.is_some_and(|deadline| ready_at > deadline) {
+            return None;
+        }
+        *tat = Some(start + self.interval);
+        Some(ready_at - now)
+    }
+}
+
+fn rate_limited(class: OperationClass) -> SdkError {
+    SdkError::new(StatusCode::TOO_MANY_REQUESTS, SdkInnerError::RateLimited(class))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let bucket = TokenBucket::new(RateLimit::per_second(2));
+        let now = Instant::now();
+        assert_eq!(Some(Duration::ZERO), bucket.reserve(now, None));
+        assert_eq!(Some(Duration::ZERO), bucket.reserve(now, None));
+        assert_eq!(None, bucket.reserve(now, Some(now + Duration::from_millis(100))));
+        assert_eq!(Some(Duration::from_millis(500)), bucket.reserve(now, None));
+        assert_eq!(Some(Duration::from_millis(1000)), bucket.reserve(now, None));
+        let later = now + Duration::from_secs(10);
+        assert_eq!(Some(Duration::ZERO), bucket.reserve(later, None));
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        let limiter = Limiter::new(LimitPolicy {
+            write: Budget::default().max_in_flight(1),
+            max_wait: Some(Duration::from_millis(10)),
+            ..Default::default()
+        });
+        let permit = limiter.acquire(OperationClass::Write).await.unwrap();
+        let err = limiter.acquire(OperationClass::Write).await.unwrap_err();
+        assert!(err.is_rate_limited());
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, err.code);
+        limiter.acquire(OperationClass::Read).await.unwrap();
+        drop(permit);
+        limiter.acquire(OperationClass::Write).await.unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+use std::{fmt::Display, time::Duration};
+
+use super::Status;
+use crate::{SdkError, SdkInnerError};
+
+/// How a request ended, used as the `outcome` label of the metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Ok,
+    CasdoorError,
+    TransportError,
+    Timeout,
+    DecodeError,
+    RateLimited,
+    CircuitOpen,
+    /// The SDK failed without a response from Casdoor, e.g. to serialize the
+    /// request or to load the config.
+    LocalError,
+}
+
+impl Outcome {
+    /// `status` is the Casdoor status of API responses.
+    pub(crate) fn of(result: Result<Option<&Status>, &SdkError>) -> Self {
+        match result {
+            Ok(None | Some(Status::Ok(_))) => Self::Ok,
+            Ok(Some(_)) => Self::CasdoorError,
+            Err(e) if e.is_rate_limited() => Self::RateLimited,
+            Err(e) if e.is_circuit_open() => Self::CircuitOpen,
+            Err(e) if e.is_timeout() => Self::Timeout,
+            Err(e) if e.is_transport() => Self::TransportError,
+            Err(e) if e.is_decode() => Self::DecodeError,
+            Err(SdkError {
+                inner: SdkInnerError::CasdoorError(_),
+                ..
+            }) => Self::CasdoorError,
+            Err(_) => Self::LocalError,
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::CasdoorError => write!(f, "casdoor_error"),
+            Self::TransportError => write!(f, "transport_error"),
+            Self::Timeout => write!(f, "timeout"),
+            Self::DecodeError => write!(f, "decode_error"),
+            Self::RateLimited => write!(f, "rate_limited"),
+            Self::CircuitOpen => write!(f, "circuit_open"),
+            Self::LocalError => write!(f, "local_error"),
+        }
+    }
+}
+
+/// Records the `casdoor_sdk_requests_total` counter and the
+/// `casdoor_sdk_request_duration_seconds` histogram of one logical operation.
+/// Without the `metrics` feature it does nothing.
+#[cfg(feature = "metrics")]
+pub(crate) fn record_request(operation: &str, outcome: Outcome, elapsed: Duration) {
+    let labels = [("operation", operation.to_owned()), ("outcome", outcome.to_string())];
+    metrics::counter!("casdoor_sdk_requests_total", &labels).increment(1);
+    metrics::histogram!("casdoor_sdk_request_duration_seconds", &labels).record(elapsed.as_secs_f64());
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) const fn record_request(_operation: &str, _outcome: Outcome, _elapsed: Duration) {}
+
+/// Derives the operation name from an API path, e.g. `get_users` from
+/// `/api/get-users?owner=built-in`.
+pub(crate) fn operation_of(url_path: &str) -> String {
+    let path = url_path.split_once('?').map_or(url_path, |(path, _)| path);
+    let path = path.strip_prefix("/api/").unwrap_or(path);
+    path.trim_matches('/').replace(['-', '/'], "_")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::default_numeric_fallback)]
+mod tests {
+    use super::*;
+    use crate::{ConfigError, StatusCode};
+
+    #[test]
+    fn test_operation_of() {
+        assert_eq!("get_users", operation_of("/api/get-users?owner=built-in&pageSize=10"));
+        assert_eq!("enforce", operation_of("/api/enforce"));
+        assert_eq!(
+            "login_oauth_access_token",
+            operation_of("/api/login/oauth/access_token")
+        );
+    }
+
+    #[test]
+    fn test_outcome() {
+        assert_eq!(Outcome::Ok, Outcome::of(Ok(None)));
+        assert_eq!(Outcome::Ok, Outcome::of(Ok(Some(&Status::Ok(String::new())))));
+        assert_eq!(
+            Outcome::CasdoorError,
+            Outcome::of(Ok(Some(&Status::Err("boom".to_owned()))))
+        );
+        let err = SdkError::new(
+            StatusCode::BAD_GATEWAY,
+            SdkInnerError::TransportError("reset".to_owned()),
+        );
+        assert_eq!(Outcome::TransportError, Outcome::of(Err(&err)));
+        let err = SdkError::from(serde_json::from_str::<i32>("<html>").unwrap_err());
+        assert_eq!(Outcome::DecodeError, Outcome::of(Err(&err)));
+        assert_eq!(Outcome::CasdoorError, Outcome::of(Err(&SdkError::casdoor("boom"))));
+        let err = SdkError::from(serde_urlencoded::to_string("plain").unwrap_err());
+        assert_eq!(Outcome::LocalError, Outcome::of(Err(&err)));
+        let err = SdkError::from(ConfigError::MissingField("endpoint"));
+        assert_eq!(Outcome::LocalError, Outcome::of(Err(&err)));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_record_request() {
+        let recorder = TestRecorder::default();
+        let (sdk, transport) = crate::test_support::mock_sdk();
+        transport.push_json(&serde_json::json!({"status": "ok", "msg": "", "data": 1}));
+        transport.push_json(&serde_json::json!({"status": "error", "msg": "Unauthorized operation"}));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        metrics::with_local_recorder(&recorder, || {
+            runtime.block_on(async {
+                sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+                sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+            });
+        });
+        assert_eq!(
+            vec![
+                "casdoor_sdk_requests_total{operation=get_user_count,outcome=ok} +1",
+                "casdoor_sdk_request_duration_seconds{operation=get_user_count,outcome=ok}",
+                "casdoor_sdk_requests_total{operation=get_user_count,outcome=casdoor_error} +1",
+                "casdoor_sdk_request_duration_seconds{operation=get_user_count,outcome=casdoor_error}",
+            ],
+            *recorder.events.lock().unwrap()
+        );
+    }
+
+    /// Records each counter increment and histogram sample as a line.
+    #[cfg(feature = "metrics")]
+    #[derive(Debug, Default)]
+    struct TestRecorder {
+        events: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[cfg(feature = "metrics")]
+    #[derive(Debug)]
+    struct TestHandle {
+        key: String,
+        events: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[cfg(feature = "metrics")]
+    impl TestRecorder {
+        fn handle(&self, key: &metrics::Key) -> std::sync::Arc<TestHandle> {
+            let labels: Vec<_> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            std::sync::Arc::new(TestHandle {
+                key: format!("{}{{{}}}", key.name(), labels.join(",")),
+                events: self.events.clone(),
+            })
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    impl metrics::Recorder for TestRecorder {
+        fn describe_counter(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+
+        fn describe_gauge(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+
+        fn describe_histogram(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+
+        fn register_counter(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Counter {
+            metrics::Counter::from_arc(self.handle(key))
+        }
+
+        fn register_gauge(&self, _: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Gauge {
+            metrics::Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> metrics::Histogram {
+            metrics::Histogram::from_arc(self.handle(key))
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    impl metrics::CounterFn for TestHandle {
+        fn increment(&self, value: u64) {
+            self.events.lock().unwrap().push(format!("{} +{value}", self.key));
+        }
+
+        fn absolute(&self, value: u64) {
+            self.events.lock().unwrap().push(format!("{} ={value}", self.key));
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    impl metrics::HistogramFn for TestHandle {
+        fn record(&self, _value: f64) {
+            self.events.lock().unwrap().push(self.key.clone());
+        }
+    }
+}
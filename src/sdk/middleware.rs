@@ -0,0 +1,48 @@
+use std::{fmt::Debug, time::Duration};
+
+use serde_json::Value;
+
+use super::{ApiResponse, HttpRequest, HttpResponse};
+use crate::SdkResult;
+
+/// Hooks around every request made by a [`Sdk`](crate::Sdk), registered with
+/// [`SdkBuilder::middleware`](crate::SdkBuilder::middleware).
+///
+/// Middlewares run in the order they were registered, for API calls of
+/// [`Sdk`](crate::Sdk) as well as the OAuth token calls of
+/// [`AuthSdk`](crate::AuthSdk).
+pub trait Middleware: Debug + Send + Sync {
+    /// Called once before the request is sent; retries reuse the modified
+    /// request.
+    fn on_request(&self, req: &mut HttpRequest) {
+        let _ = req;
+    }
+
+    /// Called with the decoded Casdoor response or the error of an API call.
+    /// Rewriting `res` changes what the caller gets.
+    fn on_response(&self, req: &HttpRequest, info: &ResponseInfo, res: &mut SdkResult<ApiResponse<Value, Value>>) {
+        let _ = (req, info, res);
+    }
+
+    /// Called with the response or the error of a
+    /// [`Sdk::request_raw`](crate::Sdk::request_raw) call.
+    fn on_raw_response(&self, req: &HttpRequest, info: &ResponseInfo, res: &mut SdkResult<HttpResponse>) {
+        let _ = (req, info, res);
+    }
+
+    /// Called with the raw response or the error of an OAuth token call.
+    fn on_oauth_response(&self, req: &HttpRequest, info: &ResponseInfo, res: &mut SdkResult<HttpResponse>) {
+        let _ = (req, info, res);
+    }
+}
+
+/// Facts about a finished request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResponseInfo {
+    /// Time from sending the request until the response was received,
+    /// including retries.
+    pub elapsed: Duration,
+    /// Number of times the request was sent.
+    pub attempts: u32,
+}
@@ -1,27 +1,89 @@
+mod breaker;
+mod builder;
+mod endpoints;
 mod errors;
+mod limit;
+mod metrics;
+mod middleware;
 mod models;
-use std::{ops::Deref, sync::Arc};
+mod reload;
+mod retry;
+mod trace;
+mod transport;
+use std::{
+    borrow::Cow,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::{Duration, Instant},
+};
 
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+pub(crate) use breaker::CircuitBreaker;
+pub use breaker::{CircuitBreakerPolicy, HealthState};
+pub use builder::*;
 use cubix::MaybeString;
+pub(crate) use endpoints::Endpoints;
+pub use endpoints::{EndpointHealth, FailoverPolicy};
 pub use errors::*;
+pub(crate) use limit::Limiter;
+pub use limit::{Budget, LimitPolicy, OperationClass, RateLimit};
+pub(crate) use metrics::{Outcome, operation_of, record_request};
+pub use middleware::*;
 pub use models::*;
+pub(crate) use reload::ConfigState;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
+pub use retry::*;
 use serde::{
     Deserialize, Serialize,
-    de::{DeserializeOwned, Deserializer},
+    de::{DeserializeOwned, Deserializer, IgnoredAny},
     ser::{SerializeStruct, Serializer},
 };
+use serde_json::Value;
+pub(crate) use trace::RequestTrace;
+pub use transport::*;
 
-use crate::{Config, Method, SdkResult, StatusCode};
+use crate::{
+    ApiCredentials, Config, DiscoveryCache, DiscoveryPolicy, JwksPolicy, JwksStore, JwtAlgorithm, Method, SdkResult,
+    StatusCode,
+};
 
 #[derive(Debug, Clone)]
 pub struct Sdk {
-    config: Arc<Config>,
+    inner: Arc<SdkInner>,
+    credentials: Option<Arc<ApiCredentials>>,
+    scope: Option<Arc<Scope>>,
+}
+
+/// The organization and application a handle works on, overriding the ones
+/// of the [`Config`]. `None` takes the one of the current config.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    org_name: Option<String>,
+    app_name: Option<String>,
 }
-impl Deref for Sdk {
-    type Target = Config;
 
-    fn deref(&self) -> &Self::Target {
-        &self.config
+#[derive(Debug)]
+struct SdkInner {
+    state: RwLock<ConfigState>,
+    transport: Arc<dyn Transport>,
+    retry_policy: RetryPolicy,
+    limiter: Limiter,
+    failover_policy: FailoverPolicy,
+    certificate_overlap: Duration,
+    circuit_breaker: Option<CircuitBreaker>,
+    jwt_algorithms: Vec<JwtAlgorithm>,
+    jwks: Option<JwksStore>,
+    discovery: DiscoveryCache,
+    error_mod_path: Option<ModPath>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl SdkInner {
+    fn read_state(&self) -> RwLockReadGuard<'_, ConfigState> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_state(&self) -> RwLockWriteGuard<'_, ConfigState> {
+        self.state.write().unwrap_or_else(PoisonError::into_inner)
     }
 }
 
@@ -34,12 +96,214 @@ impl Config {
 pub const NO_BODY: Body<'static, ()> = Body::NoBody::<'static, ()>;
 
 impl Sdk {
+    /// Create a new Sdk with a default HTTP client.
+    /// Use [`Sdk::builder`] to tune timeouts, proxies, certificates and
+    /// headers. An invalid certificate is only reported when verifying a
+    /// token; [`SdkBuilder::build`] rejects it upfront.
     pub fn new(config: Config) -> Self {
+        SdkBuilder::new(config).finish(Arc::new(ReqwestTransport::default()))
+    }
+
+    pub fn builder(config: Config) -> SdkBuilder {
+        SdkBuilder::new(config)
+    }
+
+    /// The current config, see [`Sdk::reload`].
+    pub fn config(&self) -> Arc<Config> {
+        self.inner.read_state().config.clone()
+    }
+
+    pub(crate) fn endpoints(&self) -> Arc<Endpoints> {
+        self.inner.read_state().endpoints.clone()
+    }
+
+    /// The config and its endpoints, read together so that a request is
+    /// built and routed against the same [`Sdk::reload`].
+    pub(crate) fn snapshot(&self) -> (Arc<Config>, Arc<Endpoints>) {
+        let state = self.inner.read_state();
+        (state.config.clone(), state.endpoints.clone())
+    }
+
+    /// The transport shared by all clones of this Sdk.
+    pub fn transport(&self) -> &Arc<dyn Transport> {
+        &self.inner.transport
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.inner.retry_policy
+    }
+
+    pub fn limit_policy(&self) -> &LimitPolicy {
+        self.inner.limiter.policy()
+    }
+
+    pub fn circuit_breaker_policy(&self) -> Option<&CircuitBreakerPolicy> {
+        self.inner.circuit_breaker.as_ref().map(CircuitBreaker::policy)
+    }
+
+    /// The algorithms JWTs may be signed with, see
+    /// [`SdkBuilder::jwt_algorithms`].
+    pub fn jwt_algorithms(&self) -> &[JwtAlgorithm] {
+        &self.inner.jwt_algorithms
+    }
+
+    pub fn jwks_policy(&self) -> Option<&JwksPolicy> {
+        self.inner.jwks.as_ref().map(JwksStore::policy)
+    }
+
+    pub(crate) fn jwks(&self) -> Option<&JwksStore> {
+        self.inner.jwks.as_ref()
+    }
+
+    pub fn discovery_policy(&self) -> &DiscoveryPolicy {
+        self.inner.discovery.policy()
+    }
+
+    pub(crate) fn discovery(&self) -> &DiscoveryCache {
+        &self.inner.discovery
+    }
+
+    /// Whether Casdoor is considered available, e.g. for readiness probes.
+    /// Always [`HealthState::Closed`] without a circuit breaker.
+    pub fn health_state(&self) -> HealthState {
+        self.inner
+            .circuit_breaker
+            .as_ref()
+            .map_or(HealthState::Closed, CircuitBreaker::health_state)
+    }
+
+    pub fn failover_policy(&self) -> &FailoverPolicy {
+        &self.inner.failover_policy
+    }
+
+    /// How long a certificate replaced by [`Sdk::reload`] is still accepted.
+    pub fn certificate_overlap(&self) -> Duration {
+        self.inner.certificate_overlap
+    }
+
+    /// The health of the endpoint and the fallback endpoints of the
+    /// [`Config`], in order.
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints().health()
+    }
+
+    /// Requests the probe path of every endpoint and marks those that answer
+    /// with a 2xx status healthy, the others unhealthy.
+    pub async fn probe_endpoints(&self) -> Vec<EndpointHealth> {
+        let endpoints = self.endpoints();
+        for (index, endpoint) in endpoints.urls().iter().enumerate() {
+            let req = HttpRequest {
+                method: Method::GET,
+                url: format!("{endpoint}{}", endpoints.policy().probe_path),
+                headers: HeaderMap::new(),
+                body: Vec::new(),
+            };
+            let healthy = self
+                .transport()
+                .send(req)
+                .await
+                .is_ok_and(|res| res.status.is_success());
+            endpoints.mark(index, healthy);
+        }
+        endpoints.health()
+    }
+
+    /// Probes the endpoints every `interval`, see [`Sdk::probe_endpoints`].
+    /// Never returns; spawn it on the runtime and drop it to stop probing.
+    pub async fn run_endpoint_probes(&self, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            self.probe_endpoints().await;
+        }
+    }
+
+    /// Suffix of the error codes of [`SdkError::into_api_error`].
+    pub fn error_mod_path(&self) -> ModPath {
+        self.inner.error_mod_path.unwrap_or_else(default_error_mod_path)
+    }
+
+    /// The credentials sent with API requests.
+    pub fn credentials(&self) -> ApiCredentials {
+        self.credentials_in(&self.config())
+    }
+
+    fn credentials_in(&self, config: &Config) -> ApiCredentials {
+        match &self.credentials {
+            Some(credentials) => ApiCredentials::clone(credentials),
+            None => config.credentials().clone(),
+        }
+    }
+
+    /// Returns a handle that sends API requests with other credentials, e.g.
+    /// as a logged-in user. It shares everything else with this Sdk.
+    pub fn with_credentials(&self, credentials: ApiCredentials) -> Self {
+        Self {
+            credentials: Some(Arc::new(credentials)),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a handle that works on another organization: it is the owner
+    /// in every URL, [`Sdk::id`] and [`Sdk::user_id_query`]. It shares the
+    /// HTTP client and credentials with this Sdk.
+    pub fn with_org(&self, org_name: impl Into<String>) -> Self {
+        let scope = self.scope.as_deref().cloned().unwrap_or_default();
+        Self {
+            scope: Some(Arc::new(Scope {
+                org_name: Some(org_name.into()),
+                ..scope
+            })),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a handle that works on another application of the
+    /// organization, e.g. for its sign-up URL. The client ID and secret are
+    /// still those of the [`Config`].
+    pub fn with_app(&self, app_name: impl Into<String>) -> Self {
+        let scope = self.scope.as_deref().cloned().unwrap_or_default();
         Self {
-            config: Arc::new(config),
+            scope: Some(Arc::new(Scope {
+                app_name: Some(app_name.into()),
+                ..scope
+            })),
+            ..self.clone()
+        }
+    }
+
+    /// The organization of this handle, see [`Sdk::with_org`].
+    pub fn org_name(&self) -> String {
+        match self.scope.as_ref().and_then(|scope| scope.org_name.as_ref()) {
+            Some(org_name) => org_name.clone(),
+            None => self.config().org_name().clone(),
         }
     }
 
+    /// The application of this handle, see [`Sdk::with_app`].
+    pub fn app_name(&self) -> Option<String> {
+        match self.scope.as_ref().and_then(|scope| scope.app_name.as_ref()) {
+            Some(app_name) => Some(app_name.clone()),
+            None => self.config().app_name().clone(),
+        }
+    }
+
+    #[deprecated(note = "the config is reloadable, use `sdk.config().endpoint()`")]
+    pub fn endpoint(&self) -> String {
+        self.config().endpoint().clone()
+    }
+
+    #[deprecated(note = "the config is reloadable, use `sdk.config().client_id()`")]
+    pub fn client_id(&self) -> String {
+        self.config().client_id().clone()
+    }
+
+    #[deprecated(note = "the config is reloadable, use `sdk.config().certificate()`")]
+    pub fn certificate(&self) -> String {
+        self.config().certificate().clone()
+    }
+
     pub fn id(&self, name: &str) -> String {
         format!("{}/{}", self.org_name(), name)
     }
@@ -61,18 +325,264 @@ impl Sdk {
         Data: DeserializeOwned,
         Data2: DeserializeOwned,
     {
-        let url = self.config.endpoint().clone() + url_path.as_ref();
-        println!("{url}");
-        let mut req = reqwest::Client::new().request(method, url).basic_auth(
-            self.config.client_id().clone(),
-            Some(self.config.client_secret().clone()),
-        );
-        match body {
-            Body::Json(v) => req = req.json(v),
-            Body::Form(v) => req = req.form(v),
-            Body::NoBody => {}
+        self.request_with(method, url_path, body, RequestOptions::default())
+            .await
+    }
+
+    pub async fn request_with<Data, Data2>(
+        &self,
+        method: Method,
+        url_path: impl AsRef<str>,
+        body: Body<'_, impl Serialize>,
+        options: RequestOptions,
+    ) -> SdkResult<ApiResponse<Data, Data2>>
+    where
+        Data: DeserializeOwned,
+        Data2: DeserializeOwned,
+    {
+        let url_path = url_path.as_ref();
+        let operation = options
+            .operation
+            .clone()
+            .unwrap_or_else(|| operation_of(url_path).into());
+        let (config, endpoints) = self.snapshot();
+        let req = self.prepare_request(&config, method, url_path, body)?;
+        let trace = RequestTrace::new(&req, &operation);
+        let context = RequestContext::new(&req, &operation, options.model);
+        let (res, info) = self.execute(&req, &endpoints, &options, &trace).await;
+        let res = if self.middlewares().is_empty() {
+            res.and_then(|res| ApiResponse::from_http(res).map_err(|e| e.with_attempts(info.attempts)))
+                .map(|res| res.with_context(context.clone()))
+        } else {
+            let mut res = res.and_then(|res| {
+                ApiResponse::<Value, Value>::from_http(res).map_err(|e| e.with_attempts(info.attempts))
+            });
+            res = res.map(|res| res.with_context(context.clone()));
+            for middleware in self.middlewares() {
+                middleware.on_response(&req, &info, &mut res);
+            }
+            res.and_then(|res| res.decode().map_err(|e| SdkError::from(e).with_attempts(info.attempts)))
+        };
+        let res = res.map_err(|e| e.with_context(Some(context)));
+        let status = res.as_ref().map(|res| Some(&res.status));
+        record_request(&operation, Outcome::of(status), info.elapsed);
+        trace.finish(status);
+        res
+    }
+
+    /// Sends a request like [`Sdk::request_with`], but returns the HTTP
+    /// response as is instead of decoding a Casdoor [`ApiResponse`]. Non-2xx
+    /// statuses are not errors here.
+    pub async fn request_raw(
+        &self,
+        method: Method,
+        url_path: impl AsRef<str>,
+        body: Body<'_, impl Serialize>,
+        options: RequestOptions,
+    ) -> SdkResult<HttpResponse> {
+        let url_path = url_path.as_ref();
+        let operation = options
+            .operation
+            .clone()
+            .unwrap_or_else(|| operation_of(url_path).into());
+        let (config, endpoints) = self.snapshot();
+        let req = self.build_request(&config, method, url_path, body)?;
+        self.send_raw(req, &endpoints, options.operation(operation)).await
+    }
+
+    /// Sends a request built by the caller like [`Sdk::request_raw`], e.g. to
+    /// a URL of the discovery document. The credentials of the Sdk are not
+    /// added, and the operation must be set in the options.
+    pub(crate) async fn send_raw(
+        &self,
+        mut req: HttpRequest,
+        endpoints: &Endpoints,
+        options: RequestOptions,
+    ) -> SdkResult<HttpResponse> {
+        for middleware in self.middlewares() {
+            middleware.on_request(&mut req);
+        }
+        let operation = options.operation.clone().unwrap_or_default();
+        let trace = RequestTrace::new(&req, &operation);
+        let (res, info) = self.execute(&req, endpoints, &options, &trace).await;
+        let mut res = res.map_err(|e| e.with_context(Some(RequestContext::new(&req, &operation, options.model))));
+        for middleware in self.middlewares() {
+            middleware.on_raw_response(&req, &info, &mut res);
+        }
+        record_request(&operation, Outcome::of(res.as_ref().map(|_| None)), info.elapsed);
+        trace.finish(res.as_ref().map(|_| None));
+        res
+    }
+
+    /// Builds the request and runs the `on_request` middlewares.
+    fn prepare_request(
+        &self,
+        config: &Config,
+        method: Method,
+        url_path: &str,
+        body: Body<'_, impl Serialize>,
+    ) -> SdkResult<HttpRequest> {
+        let mut req = self.build_request(config, method, url_path, body)?;
+        for middleware in self.middlewares() {
+            middleware.on_request(&mut req);
+        }
+        Ok(req)
+    }
+
+    /// Sends a prepared request within its trace.
+    pub(crate) async fn execute(
+        &self,
+        req: &HttpRequest,
+        endpoints: &Endpoints,
+        options: &RequestOptions,
+        trace: &RequestTrace,
+    ) -> (SdkResult<HttpResponse>, ResponseInfo) {
+        let start = Instant::now();
+        let class = options.class.unwrap_or_else(|| OperationClass::of(&req.method));
+        let res = trace
+            .instrument(self.send(req, endpoints, options.idempotency, class))
+            .await;
+        let info = ResponseInfo {
+            elapsed: start.elapsed(),
+            attempts: res.as_ref().map_or_else(SdkError::attempts, |(_, attempts)| *attempts),
+        };
+        let res = res.map(|(res, attempts)| {
+            trace.record_http(res.status, attempts);
+            res
+        });
+        (res, info)
+    }
+
+    pub fn middlewares(&self) -> &[Arc<dyn Middleware>] {
+        &self.inner.middlewares
+    }
+
+    /// Sends the request through the transport, retrying it according to the
+    /// [`RetryPolicy`] and the [`LimitPolicy`] of the class, unless the
+    /// circuit breaker is open. Each attempt of a request to the endpoint
+    /// goes to the first healthy one of the `endpoints` the request was built
+    /// against, and idempotent requests fail over to the next one right
+    /// away, see [`FailoverPolicy::try_every_endpoint`]. Requests to other
+    /// URLs, such as those of the discovery document, leave the endpoint
+    /// health as is. Returns the response and the number of attempts.
+    pub(crate) async fn send(
+        &self,
+        req: &HttpRequest,
+        endpoints: &Endpoints,
+        idempotency: Idempotency,
+        class: OperationClass,
+    ) -> SdkResult<(HttpResponse, u32)> {
+        let policy = self.retry_policy();
+        let routed = endpoints.routes(&req.url);
+        let mut max_attempts = policy.max_attempts_for(&req.method, idempotency);
+        if routed && endpoints.policy().try_every_endpoint && idempotency.is_idempotent(&req.method) {
+            max_attempts = max_attempts.max(u32::try_from(endpoints.len()).unwrap_or(u32::MAX));
+        }
+        let mut attempt = 1;
+        let breaker = self.inner.circuit_breaker.as_ref();
+        loop {
+            if let Some(breaker) = breaker {
+                breaker.acquire().map_err(|e| e.with_attempts(attempt))?;
+            }
+            let permit = self
+                .inner
+                .limiter
+                .acquire(class)
+                .await
+                .map_err(|e| e.with_attempts(attempt))?;
+            let endpoint = routed.then(|| endpoints.select());
+            let res = match endpoint {
+                Some(endpoint) => self.transport().send(endpoints.route(req, endpoint)).await,
+                None => self.transport().send(req.clone()).await,
+            };
+            drop(permit);
+            if let Some(endpoint) = endpoint {
+                endpoints.mark(endpoint, !res.as_ref().is_err_and(SdkError::is_transport));
+            }
+            if let Some(breaker) = breaker {
+                breaker.record(&res);
+            }
+            let retry = attempt < max_attempts
+                && match &res {
+                    Ok(res) => policy.retry_on.contains(&res.status),
+                    Err(e) => e.is_transport(),
+                };
+            let delay = retry
+                .then(|| policy.delay(attempt, res.as_ref().ok().and_then(HttpResponse::retry_after)))
+                .flatten();
+            let Some(delay) = delay else {
+                return res.map(|res| (res, attempt)).map_err(|e| e.with_attempts(attempt));
+            };
+            if endpoint.is_none_or(|endpoint| endpoints.select() == endpoint) {
+                tokio::time::sleep(delay).await;
+            }
+            attempt += 1;
+        }
+    }
+
+    fn build_request(
+        &self,
+        config: &Config,
+        method: Method,
+        url_path: &str,
+        body: Body<'_, impl Serialize>,
+    ) -> SdkResult<HttpRequest> {
+        let mut url = config.endpoint().clone() + url_path;
+        let mut headers = HeaderMap::new();
+        match &self.credentials_in(config) {
+            ApiCredentials::ClientBasic => {
+                headers.insert(AUTHORIZATION, Self::basic_auth_header(config)?);
+            }
+            ApiCredentials::AccessKey {
+                access_key,
+                access_secret,
+            } => {
+                url.push(if url.contains('?') { '&' } else { '?' });
+                url.push_str(&serde_urlencoded::to_string([
+                    ("accessKey", access_key),
+                    ("accessSecret", access_secret.expose_secret()),
+                ])?);
+            }
+            ApiCredentials::Bearer { token } => {
+                let mut value = HeaderValue::try_from(format!("Bearer {}", token.expose_secret()))
+                    .map_err(|e| SdkError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+                value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, value);
+            }
         }
-        Ok(req.send().await?.json::<ApiResponse<Data, Data2>>().await?)
+        let body = match body {
+            Body::Json(v) => {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                serde_json::to_vec(v)
+                    .map_err(|e| SdkError::new(StatusCode::BAD_REQUEST, SdkInnerError::SerdeJsonError(e)))?
+            }
+            Body::Form(v) => {
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                );
+                serde_urlencoded::to_string(v)?.into_bytes()
+            }
+            Body::NoBody => Vec::new(),
+        };
+        Ok(HttpRequest {
+            method,
+            url,
+            headers,
+            body,
+        })
+    }
+
+    fn basic_auth_header(config: &Config) -> SdkResult<HeaderValue> {
+        let credentials = BASE64.encode(format!(
+            "{}:{}",
+            config.client_id(),
+            config.client_secret().expose_secret()
+        ));
+        let mut value = HeaderValue::try_from(format!("Basic {credentials}"))
+            .map_err(|e| SdkError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+        value.set_sensitive(true);
+        Ok(value)
     }
 
     pub async fn request_data<Data>(
@@ -109,10 +619,17 @@ impl Sdk {
                 }
             }
         }
-        self.request_data::<ModelActionAffect>(Method::POST, url_path, Body::Json(&args.model))
-            .await?
-            .into_data_default()
-            .map(|v| v.is_affected())
+        self.request_with::<ModelActionAffect, ()>(
+            Method::POST,
+            url_path,
+            Body::Json(&args.model),
+            RequestOptions::new()
+                .operation(format!("modify_model:{}:{}", args.action, T::ident()))
+                .model(T::ident()),
+        )
+        .await?
+        .into_data_default()
+        .map(|v| v.is_affected())
     }
 
     pub async fn add_model<T: Model>(&self, args: ModelAddArgs<T>) -> SdkResult<bool> {
@@ -143,20 +660,22 @@ impl Sdk {
     }
 
     pub async fn get_model_by_name<M: Model>(&self, name: String) -> Result<Option<M>, SdkError> {
-        self.request_data(
+        self.request_with::<M, ()>(
             Method::GET,
             format!("/api/get-{}?id={}", M::ident(), self.id(&name)),
             NO_BODY,
+            RequestOptions::new().model(M::ident()),
         )
         .await?
         .into_data()
     }
 
     pub async fn get_default_model<M: Model>(&self, name: String) -> Result<Option<M>, SdkError> {
-        self.request_data(
+        self.request_with::<M, ()>(
             Method::GET,
             format!("/api/get-default-{}?id={}", M::ident(), self.id(&name)),
             NO_BODY,
+            RequestOptions::new().model(M::ident()),
         )
         .await?
         .into_data()
@@ -174,10 +693,15 @@ impl Sdk {
             "get-".to_owned()
         } + M::plural_ident();
 
-        self.request(Method::GET, self.get_url_path(ident, true, query_args)?, NO_BODY)
-            .await?
-            .into_result_default()
-            .map(Into::into)
+        self.request_with(
+            Method::GET,
+            self.get_url_path(ident, true, query_args)?,
+            NO_BODY,
+            RequestOptions::new().model(M::ident()),
+        )
+        .await?
+        .into_result_default()
+        .map(Into::into)
     }
 
     pub fn get_url_path(
@@ -213,6 +737,54 @@ pub enum Body<'a, T = ()> {
     NoBody,
 }
 
+/// Per-request options for [`Sdk::request_with`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RequestOptions {
+    idempotency: Idempotency,
+    operation: Option<Cow<'static, str>>,
+    class: Option<OperationClass>,
+    model: Option<&'static str>,
+}
+
+impl RequestOptions {
+    pub const fn new() -> Self {
+        Self {
+            idempotency: Idempotency::Auto,
+            operation: None,
+            class: None,
+            model: None,
+        }
+    }
+
+    /// Name of the logical operation used in metrics and traces, such as
+    /// `modify_model:update:user`. Derived from the URL path by default, e.g.
+    /// `get_users` for `/api/get-users`.
+    pub fn operation(mut self, operation: impl Into<Cow<'static, str>>) -> Self {
+        self.operation = Some(operation.into());
+        self
+    }
+
+    /// Whether the request may be retried, see [`RetryPolicy`].
+    pub const fn idempotency(mut self, idempotency: Idempotency) -> Self {
+        self.idempotency = idempotency;
+        self
+    }
+
+    /// The budget the request is charged against, see [`LimitPolicy`].
+    /// Derived from the method by default.
+    pub const fn class(mut self, class: OperationClass) -> Self {
+        self.class = Some(class);
+        self
+    }
+
+    /// The [`Model::ident`] of model operations, reported in the
+    /// [`RequestContext`] of errors.
+    pub const fn model(mut self, ident: &'static str) -> Self {
+        self.model = Some(ident);
+        self
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", default)]
 pub struct ApiResponse<Data, Data2 = ()> {
@@ -222,6 +794,10 @@ pub struct ApiResponse<Data, Data2 = ()> {
     #[serde(flatten)]
     pub status: Status,
     pub sub: String,
+    #[serde(skip)]
+    http: ResponseHead,
+    #[serde(skip)]
+    context: Option<RequestContext>,
 }
 
 impl<Data, Data2> Default for ApiResponse<Data, Data2> {
@@ -232,6 +808,8 @@ impl<Data, Data2> Default for ApiResponse<Data, Data2> {
             name: Default::default(),
             status: Default::default(),
             sub: Default::default(),
+            http: Default::default(),
+            context: Default::default(),
         }
     }
 }
@@ -295,15 +873,88 @@ impl Serialize for Status {
     }
 }
 
+impl ApiResponse<Value, Value> {
+    /// Deserializes the untyped data into the given types.
+    pub fn decode<Data, Data2>(self) -> serde_json::Result<ApiResponse<Data, Data2>>
+    where
+        Data: DeserializeOwned,
+        Data2: DeserializeOwned,
+    {
+        Ok(ApiResponse {
+            data: self.data.map(serde_json::from_value).transpose()?,
+            data2: self.data2.map(serde_json::from_value).transpose()?,
+            name: self.name,
+            status: self.status,
+            sub: self.sub,
+            http: self.http,
+            context: self.context,
+        })
+    }
+}
+
 impl<Data, Data2> ApiResponse<Data, Data2> {
+    /// Decodes the body of a Casdoor API response, keeping its status and
+    /// headers. Bodies that are not JSON, and error statuses without a Casdoor
+    /// body, such as the error page of a proxy, become an
+    /// [`SdkInnerError::UnexpectedResponse`].
+    pub fn from_http(res: HttpResponse) -> SdkResult<Self>
+    where
+        Data: DeserializeOwned,
+        Data2: DeserializeOwned,
+    {
+        match serde_json::from_slice::<Self>(&res.body) {
+            Ok(api_response) => Ok(Self {
+                http: ResponseHead {
+                    status: res.status,
+                    headers: res.headers,
+                },
+                ..api_response
+            }),
+            Err(e) => {
+                if res.status.is_success() && serde_json::from_slice::<IgnoredAny>(&res.body).is_ok() {
+                    Err(e.into())
+                } else {
+                    Err(SdkError::unexpected_response(&res))
+                }
+            }
+        }
+    }
+
+    /// The HTTP status and headers of the response.
+    pub const fn http(&self) -> &ResponseHead {
+        &self.http
+    }
+
+    pub const fn http_status(&self) -> StatusCode {
+        self.http.status
+    }
+
+    /// The request this is the response to, with the `name` and `sub` of the
+    /// response.
+    pub fn context(&self) -> Option<RequestContext> {
+        let non_empty = |s: &String| (!s.is_empty()).then(|| s.clone());
+        self.context.clone().map(|context| RequestContext {
+            name: non_empty(&self.name),
+            sub: non_empty(&self.sub),
+            ..context
+        })
+    }
+
+    pub(crate) fn with_context(self, context: RequestContext) -> Self {
+        Self {
+            context: Some(context),
+            ..self
+        }
+    }
+
     pub fn into_result(self) -> SdkResult<(Option<Data>, Option<Data2>)> {
+        let context = self.context();
         match self.status {
             Status::Ok(_) => Ok((self.data, self.data2)),
-            Status::Err(e) => Err(SdkError::new(StatusCode::INTERNAL_SERVER_ERROR, e)),
-            Status::Other { status, msg } => Err(SdkError::new(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Unknown: status={status}, msg={msg}"),
-            )),
+            Status::Err(e) => Err(SdkError::casdoor(e).with_context(context)),
+            Status::Other { status, msg } => {
+                Err(SdkError::casdoor(format!("Unknown: status={status}, msg={msg}")).with_context(context))
+            }
         }
     }
 
@@ -322,8 +973,9 @@ impl<Data, Data2> ApiResponse<Data, Data2> {
     }
 
     pub fn into_data_value(self) -> SdkResult<Data> {
+        let context = self.context();
         let (data, _) = self.into_result()?;
-        data.ok_or(SdkError::new(StatusCode::NOT_FOUND, "Unexpected empty data."))
+        data.ok_or_else(|| SdkError::new(StatusCode::NOT_FOUND, "Unexpected empty data.").with_context(context))
     }
 
     pub fn into_data_default(self) -> SdkResult<Data>
@@ -340,8 +992,9 @@ impl<Data, Data2> ApiResponse<Data, Data2> {
     }
 
     pub fn into_data2_value(self) -> SdkResult<Data2> {
+        let context = self.context();
         let (_, data2) = self.into_result()?;
-        data2.ok_or(SdkError::new(StatusCode::NOT_FOUND, "Unexpected empty data2."))
+        data2.ok_or_else(|| SdkError::new(StatusCode::NOT_FOUND, "Unexpected empty data2.").with_context(context))
     }
 
     pub fn into_data2_default(self) -> SdkResult<Data2>
@@ -354,15 +1007,830 @@ impl<Data, Data2> ApiResponse<Data, Data2> {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::default_numeric_fallback)]
 mod tests {
     use std::collections::HashMap;
 
+    use serde_json::json;
+
     use super::*;
+    use crate::{
+        EnforceArgs, EnforceQueryArgs, TokenResponse, UserQueryArgs,
+        test_support::{mock_sdk, mock_sdk_with},
+    };
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: std::time::Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    fn connection_refused() -> SdkError {
+        SdkError::new(
+            StatusCode::BAD_GATEWAY,
+            SdkInnerError::TransportError("connection refused".to_owned()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_get_users() {
+        let (sdk, transport) = mock_sdk();
+        transport.push_json(
+            &json!({"status": "ok", "msg": "", "data": [{"owner": "built-in", "name": "alice"}], "data2": 1}),
+        );
+        let res = sdk
+            .get_users(UserQueryArgs {
+                page_size: Some(10),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(1, res.total());
+
+        let req = transport.last_request().unwrap();
+        assert_eq!(Method::GET, req.method);
+        assert_eq!("http://casdoor.test/api/get-users?owner=built-in&pageSize=10", req.url);
+        assert_eq!("Basic Y2lkOnNlY3JldA==", req.headers[AUTHORIZATION]);
+        assert!(req.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_set_user_password() {
+        let (sdk, transport) = mock_sdk();
+        transport.push_json(&json!({"status": "ok", "msg": "", "data": null}));
+        sdk.set_user_password(crate::SetPasswordArgs {
+            user_name: "alice".to_owned(),
+            new_password: "n3w p@ss".to_owned(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let req = transport.last_request().unwrap();
+        assert_eq!("http://casdoor.test/api/set-password", req.url);
+        assert_eq!("application/x-www-form-urlencoded", req.headers[CONTENT_TYPE]);
+        assert_eq!("userName=alice&newPassword=n3w+p%40ss&userOwner=built-in", req.text());
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_get_organizations() {
+        let (sdk, transport) = mock_sdk();
+        // Trimmed from the response of Casdoor to
+        // `/api/get-organizations?owner=admin&organizationName=built-in`.
+        transport.push_json(&json!({
+            "status": "ok",
+            "msg": "",
+            "sub": "",
+            "name": "",
+            "data": [{
+                "owner": "admin",
+                "name": "built-in",
+                "createdTime": "2024-01-01T00:00:00Z",
+                "displayName": "Built-in Organization",
+                "websiteUrl": "https://example.com",
+                "favicon": "https://cdn.casbin.org/img/casbin/favicon.ico",
+                "passwordType": "plain",
+                "passwordSalt": "",
+                "passwordOptions": ["AtLeast6"],
+                "countryCodes": ["US"],
+                "defaultAvatar": "https://cdn.casbin.org/img/casbin.svg",
+                "defaultApplication": "",
+                "tags": [],
+                "languages": ["en", "zh"],
+                "themeData": null,
+                "masterPassword": "",
+                "initScore": 2000,
+                "enableSoftDeletion": false,
+                "isProfilePublic": true,
+                "mfaItems": [],
+                "accountItems": [{"name": "Organization", "visible": true, "viewRule": "Public", "modifyRule": "Admin"}],
+            }],
+            "data2": 1,
+        }));
+        let res = sdk
+            .get_organizations(crate::OrganizationQueryArgs {
+                sort_order: Some("ascend".to_owned()),
+                organization_name: Some("built-in".to_owned()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(1, res.total());
+        assert_eq!("built-in", res.items()[0].name());
+        assert_eq!(
+            "http://casdoor.test/api/get-organizations?owner=built-in&sortOrder=ascend&organizationName=built-in",
+            transport.last_request().unwrap().url
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_enforce() {
+        let (sdk, transport) = mock_sdk();
+        transport.push_json(&json!({"status": "ok", "msg": "", "data": [false, true]}));
+        let res = sdk
+            .enforce(EnforceArgs {
+                query: EnforceQueryArgs {
+                    permission_id: Some("built-in/p1".to_owned()),
+                    ..Default::default()
+                },
+                casbin_request: vec!["alice".to_owned(), "data1".to_owned(), "read".to_owned()],
+            })
+            .await
+            .unwrap();
+        assert!(res.allow);
+
+        let req = transport.last_request().unwrap();
+        assert_eq!(Method::POST, req.method);
+        assert_eq!(
+            "http://casdoor.test/api/enforce?owner=built-in&permissionId=built-in%2Fp1",
+            req.url
+        );
+        assert_eq!("application/json", req.headers[CONTENT_TYPE]);
+        assert_eq!(vec!["alice", "data1", "read"], req.json::<Vec<String>>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mock_transport_error() {
+        let (sdk, transport) = mock_sdk();
+        transport.push_json(&json!({"status": "error", "msg": "Unauthorized operation"}));
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert_eq!(
+            "Unauthorized operation [GET /api/get-user-count?owner=built-in&isOnline=]",
+            err.to_string()
+        );
+        assert_eq!(ErrorKind::Unauthorized, err.kind());
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, err.code);
+        assert_eq!(0, transport.pending());
+    }
+
+    #[tokio::test]
+    async fn test_error_context() {
+        let (sdk, transport) = mock_sdk();
+        let sdk = sdk.with_credentials(ApiCredentials::AccessKey {
+            access_key: "key".to_owned(),
+            access_secret: "secret".into(),
+        });
+        transport.push_json(
+            &json!({"status": "error", "msg": "The user name is already used", "name": "alice", "sub": "u-1"}),
+        );
+        let user = crate::User {
+            owner: "built-in".to_owned(),
+            name: "alice".to_owned(),
+            ..Default::default()
+        };
+        let err = sdk.add_model(ModelAddArgs { model: user }).await.unwrap_err();
+        assert_eq!(ErrorKind::Conflict, err.kind());
+        let context = err.context().unwrap();
+        assert_eq!(Method::POST, context.method);
+        assert_eq!("/api/add-user?id=***&accessKey=***&accessSecret=***", context.path);
+        assert_eq!("modify_model:add:user", context.operation);
+        assert_eq!(Some("user"), context.model);
+        assert_eq!(Some("alice"), context.name.as_deref());
+        assert_eq!(Some("u-1"), context.sub.as_deref());
+        assert_eq!(
+            "The user name is already used [POST /api/add-user?id=***&accessKey=***&accessSecret=***, model: \
+             user, name: alice, sub: u-1]",
+            err.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transport_error_redacted() {
+        #[cfg(feature = "tracing")]
+        let (logs, _guard) = {
+            let logs = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let writer = logs.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::DEBUG)
+                .with_writer(move || LogWriter(writer.clone()))
+                .finish();
+            (logs, tracing::subscriber::set_default(subscriber))
+        };
+        // Nothing listens on port 1, so the connection is refused.
+        let sdk = Config::new("http://127.0.0.1:1", "cid", "secret", "", "built-in", None)
+            .sdk_builder()
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap()
+            .with_credentials(ApiCredentials::AccessKey {
+                access_key: "k3y".to_owned(),
+                access_secret: "s3cret".into(),
+            });
+        let err = sdk
+            .get_user(crate::GetUserArgs {
+                name: Some("alice".to_owned()),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert!(err.is_transport());
+        let text = format!("{err} {err:?}");
+        #[cfg(feature = "tracing")]
+        let text = text + &String::from_utf8_lossy(&logs.lock().unwrap());
+        assert!(text.contains("accessSecret=***"), "{text}");
+        for secret in ["k3y", "s3cret", "alice"] {
+            assert!(!text.contains(secret), "{text}");
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    struct LogWriter(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    #[cfg(feature = "tracing")]
+    impl std::io::Write for LogWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_idempotent() {
+        let (sdk, transport) = mock_sdk_with(|builder| builder.retry_policy(fast_retry()));
+        transport
+            .push_error(connection_refused())
+            .push_response(HttpResponse::new(StatusCode::SERVICE_UNAVAILABLE, "<html>502</html>"))
+            .push_json(&json!({"status": "ok", "msg": "", "data": 3}));
+        assert_eq!(3, sdk.get_user_count(crate::QueryUserSet::All).await.unwrap());
+        assert_eq!(3, transport.requests().len());
+
+        transport
+            .push_error(connection_refused())
+            .push_json(&json!({"status": "ok", "msg": "", "data": [true]}));
+        assert!(sdk.enforce(EnforceArgs::default()).await.unwrap().allow);
+        assert_eq!(5, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let (sdk, transport) = mock_sdk_with(|builder| builder.retry_policy(fast_retry()));
+        for _ in 0..3 {
+            transport.push_error(connection_refused());
+        }
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert_eq!(3, err.attempts());
+        assert_eq!(
+            "connection refused (after 3 attempts) [GET /api/get-user-count?owner=built-in&isOnline=]",
+            err.to_string()
+        );
+        assert_eq!(0, transport.pending());
+    }
+
+    #[tokio::test]
+    async fn test_retry_mutation() {
+        let user = crate::User {
+            owner: "built-in".to_owned(),
+            name: "alice".to_owned(),
+            ..Default::default()
+        };
+        let (sdk, transport) = mock_sdk_with(|builder| builder.retry_policy(fast_retry()));
+        transport
+            .push_error(connection_refused())
+            .push_error(connection_refused());
+        let err = sdk.add_model(ModelAddArgs { model: user.clone() }).await.unwrap_err();
+        assert_eq!(1, err.attempts());
+        assert_eq!(1, transport.pending());
+
+        let (sdk, transport) = mock_sdk_with(|builder| {
+            builder.retry_policy(RetryPolicy {
+                retry_mutations: true,
+                ..fast_retry()
+            })
+        });
+        transport
+            .push_error(connection_refused())
+            .push_json(&json!({"status": "ok", "msg": "", "data": "Affected"}));
+        assert!(sdk.add_model(ModelAddArgs { model: user }).await.unwrap());
+        assert_eq!(2, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after() {
+        let (sdk, transport) = mock_sdk();
+        let unavailable = |retry_after: &'static str| {
+            let mut res = HttpResponse::new(StatusCode::SERVICE_UNAVAILABLE, "");
+            res.headers
+                .insert(reqwest::header::RETRY_AFTER, HeaderValue::from_static(retry_after));
+            res
+        };
+        transport
+            .push_response(unavailable("0"))
+            .push_json(&json!({"status": "ok", "msg": "", "data": 3}));
+        assert_eq!(3, sdk.get_user_count(crate::QueryUserSet::All).await.unwrap());
+        assert_eq!(2, transport.requests().len());
+
+        transport.push_response(unavailable("120"));
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, err.code);
+        assert_eq!(1, err.attempts());
+        assert_eq!(3, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let (sdk, transport) = mock_sdk_with(|builder| {
+            builder.limit_policy(LimitPolicy {
+                read: Budget::default().rate(RateLimit::new(1, std::time::Duration::from_secs(3600))),
+                max_wait: Some(std::time::Duration::ZERO),
+                ..Default::default()
+            })
+        });
+        transport
+            .push_json(&json!({"status": "ok", "msg": "", "data": 7}))
+            .push_json(&json!({"status": "ok", "msg": "", "data": [true]}));
+        assert_eq!(7, sdk.get_user_count(crate::QueryUserSet::All).await.unwrap());
+        let err = sdk
+            .with_credentials(ApiCredentials::Bearer { token: "t".into() })
+            .get_user_count(crate::QueryUserSet::All)
+            .await
+            .unwrap_err();
+        assert!(err.is_rate_limited());
+        assert_eq!(1, transport.requests().len());
+
+        // Authz checks have their own budget.
+        assert!(sdk.enforce(EnforceArgs::default()).await.unwrap().allow);
+        assert_eq!(2, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_request_raw() {
+        let (sdk, transport) = mock_sdk();
+        let mut res = HttpResponse::json(StatusCode::OK, &json!({"status": "ok", "msg": "", "data": 5}));
+        res.headers.insert("x-request-id", HeaderValue::from_static("req-1"));
+        transport
+            .push_response(res)
+            .push_response(HttpResponse::new(StatusCode::NOT_FOUND, "404 page not found"));
+
+        let res = sdk
+            .request_data::<i64>(Method::GET, "/api/get-user-count", NO_BODY)
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, res.http_status());
+        assert_eq!(Some("req-1"), res.http().request_id());
+        assert_eq!(Some("application/json"), res.http().content_type());
+
+        let res = sdk
+            .request_raw(Method::GET, "/api/unknown", NO_BODY, RequestOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, res.status);
+        assert_eq!("404 page not found", res.body_snippet());
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_response() {
+        let (sdk, transport) = mock_sdk_with(|builder| builder.retry_policy(RetryPolicy::none()));
+        let mut page = HttpResponse::new(
+            StatusCode::BAD_GATEWAY,
+            format!("<html><body>502 Bad Gateway{}</body></html>", " ".repeat(1000)),
+        );
+        page.headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        transport
+            .push_response(page)
+            .push_response(HttpResponse::new(StatusCode::OK, "<html>login</html>"))
+            .push_json(&json!({"status": "ok", "msg": "", "data": "seven"}));
+
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert!(err.is_unexpected_response() && err.is_decode());
+        assert_eq!(StatusCode::BAD_GATEWAY, err.code);
+        let SdkInnerError::UnexpectedResponse {
+            status,
+            content_type,
+            snippet,
+        } = &err.inner
+        else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(StatusCode::BAD_GATEWAY, *status);
+        assert_eq!(Some("text/html"), content_type.as_deref());
+        assert_eq!(BODY_SNIPPET_LEN, snippet.len());
+        assert!(snippet.starts_with("<html><body>502 Bad Gateway"));
+
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert!(err.is_unexpected_response());
+        assert_eq!(StatusCode::BAD_GATEWAY, err.code);
+
+        // JSON of the wrong shape stays a plain decode error.
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert!(err.is_decode() && !err.is_unexpected_response());
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let (sdk, transport) = mock_sdk_with(|builder| {
+            builder.circuit_breaker(CircuitBreakerPolicy {
+                failure_threshold: 2,
+                ..Default::default()
+            })
+        });
+        assert_eq!(HealthState::Closed, sdk.health_state());
+        transport
+            .push_error(connection_refused())
+            .push_response(HttpResponse::new(StatusCode::BAD_GATEWAY, "Bad Gateway"));
+        assert!(sdk.get_user_count(crate::QueryUserSet::All).await.is_err());
+        assert!(sdk.get_user_count(crate::QueryUserSet::All).await.is_err());
+        assert_eq!(HealthState::Open, sdk.health_state());
+
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert!(err.is_circuit_open());
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, err.code);
+        assert_eq!(2, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let transport = MockTransport::new();
+        let sdk = Config::new("http://a.casdoor.test", "cid", "secret", "", "built-in", None)
+            .with_fallback_endpoints(["http://b.casdoor.test"])
+            .sdk_builder()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let count = json!({"status": "ok", "msg": "", "data": 1});
+        transport
+            .push_error(connection_refused())
+            .push_json(&count)
+            .push_json(&count);
+        assert_eq!(1, sdk.get_user_count(crate::QueryUserSet::All).await.unwrap());
+        assert_eq!(1, sdk.get_user_count(crate::QueryUserSet::All).await.unwrap());
+        let hosts: Vec<_> = transport
+            .requests()
+            .iter()
+            .map(|req| reqwest::Url::parse(&req.url).unwrap().host_str().unwrap().to_owned())
+            .collect();
+        assert_eq!(["a.casdoor.test", "b.casdoor.test", "b.casdoor.test"], hosts.as_slice());
+        assert_eq!(
+            vec![
+                EndpointHealth {
+                    endpoint: "http://a.casdoor.test".to_owned(),
+                    healthy: false,
+                },
+                EndpointHealth {
+                    endpoint: "http://b.casdoor.test".to_owned(),
+                    healthy: true,
+                },
+            ],
+            sdk.endpoint_health()
+        );
+
+        // Mutations are not sent twice.
+        transport.push_error(connection_refused());
+        let user = crate::User {
+            owner: "built-in".to_owned(),
+            name: "alice".to_owned(),
+            ..Default::default()
+        };
+        assert!(
+            sdk.add_model(ModelAddArgs { model: user })
+                .await
+                .unwrap_err()
+                .is_transport()
+        );
+        assert_eq!(4, transport.requests().len());
+
+        transport
+            .push_response(HttpResponse::new(StatusCode::OK, "ok"))
+            .push_response(HttpResponse::new(StatusCode::OK, "ok"));
+        assert!(sdk.probe_endpoints().await.iter().all(|health| health.healthy));
+        assert_eq!("http://a.casdoor.test/api/health", transport.requests()[4].url);
+    }
+
+    #[tokio::test]
+    async fn test_failover_foreign_url() {
+        let transport = MockTransport::new();
+        let sdk = Config::new("http://a.casdoor.test", "cid", "secret", "", "built-in", None)
+            .with_fallback_endpoints(["http://b.casdoor.test"])
+            .sdk_builder()
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        transport.push_error(connection_refused());
+        let req = HttpRequest {
+            method: Method::GET,
+            url: "http://a.casdoor.test.idp.test/.well-known/jwks".to_owned(),
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+        };
+        let err = sdk
+            .send_raw(req, &sdk.endpoints(), RequestOptions::new().operation("get_jwks"))
+            .await
+            .unwrap_err();
+        assert!(err.is_transport());
+        assert_eq!(1, err.attempts());
+        assert_eq!(1, transport.requests().len());
+        assert_eq!(
+            "http://a.casdoor.test.idp.test/.well-known/jwks",
+            transport.last_request().unwrap().url
+        );
+        assert!(sdk.endpoint_health().iter().all(|health| health.healthy));
+    }
+
+    #[tokio::test]
+    async fn test_failover_retry_policy() {
+        let sdk_with = |try_every_endpoint| {
+            let transport = MockTransport::new();
+            let sdk = Config::new("http://a.casdoor.test", "cid", "secret", "", "built-in", None)
+                .with_fallback_endpoints(["http://b.casdoor.test"])
+                .sdk_builder()
+                .transport(transport.clone())
+                .retry_policy(RetryPolicy::none())
+                .failover_policy(FailoverPolicy {
+                    try_every_endpoint,
+                    ..Default::default()
+                })
+                .build()
+                .unwrap();
+            transport.push_error(connection_refused());
+            transport.push_json(&json!({"status": "ok", "msg": "", "data": 1}));
+            (sdk, transport)
+        };
+
+        let (sdk, transport) = sdk_with(true);
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        assert_eq!(2, transport.requests().len());
+
+        let (sdk, transport) = sdk_with(false);
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert!(err.is_transport());
+        assert_eq!(1, err.attempts());
+        assert_eq!(1, transport.requests().len());
+    }
+
+    /// Reloads the Sdk while the first request is being prepared.
+    #[derive(Debug, Default)]
+    struct ReloadOnRequest {
+        sdk: std::sync::OnceLock<Sdk>,
+        config: std::sync::Mutex<Option<Config>>,
+    }
+
+    impl Middleware for Arc<ReloadOnRequest> {
+        fn on_request(&self, _req: &mut HttpRequest) {
+            if let (Some(sdk), Some(config)) = (self.sdk.get(), self.config.lock().unwrap().take()) {
+                sdk.reload(config);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover_during_reload() {
+        let transport = MockTransport::new();
+        let reload = Arc::new(ReloadOnRequest::default());
+        let sdk = Config::new("http://a.casdoor.test", "cid", "secret", "", "built-in", None)
+            .with_fallback_endpoints(["http://b.casdoor.test"])
+            .sdk_builder()
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy::none())
+            .middleware(reload.clone())
+            .build()
+            .unwrap();
+        reload.sdk.set(sdk.clone()).unwrap();
+        *reload.config.lock().unwrap() = Some(Config::new(
+            "http://c.casdoor.test",
+            "cid",
+            "secret",
+            "",
+            "built-in",
+            None,
+        ));
+        transport.push_error(connection_refused());
+        transport.push_json(&json!({"status": "ok", "msg": "", "data": 1}));
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        let hosts: Vec<_> = transport
+            .requests()
+            .iter()
+            .map(|req| reqwest::Url::parse(&req.url).unwrap().host_str().unwrap().to_owned())
+            .collect();
+        assert_eq!(vec!["a.casdoor.test", "b.casdoor.test"], hosts);
+
+        transport.push_json(&json!({"status": "ok", "msg": "", "data": 1}));
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        assert!(
+            transport
+                .last_request()
+                .unwrap()
+                .url
+                .starts_with("http://c.casdoor.test/")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_public_endpoint() {
+        let config: Config = toml::from_str(
+            r#"
+            endpoint = "http://casdoor.auth.svc:8000"
+            public_endpoint = "https://login.example.com"
+            client_id = "cid"
+            client_secret = "secret"
+            certificate = ""
+            org_name = "built-in"
+            app_name = "app"
+            "#,
+        )
+        .unwrap();
+        let transport = MockTransport::new();
+        let sdk = config.sdk_builder().transport(transport.clone()).build().unwrap();
+        let authn = sdk.authn();
+        assert!(
+            authn
+                .get_signin_url("https://app.example.com/callback".to_owned())
+                .starts_with("https://login.example.com/login/oauth/authorize?client_id=cid&")
+        );
+        assert_eq!(
+            "https://login.example.com/signup/app",
+            authn.get_signup_url_enable_password()
+        );
+        assert_eq!(
+            "https://login.example.com/users/built-in/alice",
+            authn.get_user_profile_url("alice".to_owned(), None)
+        );
+        assert_eq!("https://login.example.com/account", authn.get_my_profile_url(None));
+
+        transport
+            .push_json(&json!({"status": "ok", "msg": "", "data": 3}))
+            .push_json(&json!({"access_token": "token", "token_type": "bearer"}));
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        authn.get_oauth_token("code".to_owned()).await.unwrap();
+        let requests = transport.requests();
+        assert!(
+            requests[0]
+                .url
+                .starts_with("http://casdoor.auth.svc:8000/api/get-user-count?")
+        );
+        assert_eq!(
+            "http://casdoor.auth.svc:8000/api/login/oauth/access_token",
+            requests[1].url
+        );
+    }
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        responses: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl Middleware for Arc<Recorder> {
+        fn on_request(&self, req: &mut HttpRequest) {
+            req.headers.insert("x-correlation-id", HeaderValue::from_static("abc"));
+        }
+
+        fn on_response(&self, req: &HttpRequest, info: &ResponseInfo, res: &mut SdkResult<ApiResponse<Value, Value>>) {
+            self.responses
+                .lock()
+                .unwrap()
+                .push(format!("{} {} {}", req.method, info.attempts, res.is_ok()));
+            if let Ok(ApiResponse {
+                status: Status::Err(msg),
+                ..
+            }) = res
+            {
+                *msg = format!("rewritten: {msg}");
+            }
+        }
+
+        fn on_oauth_response(&self, req: &HttpRequest, info: &ResponseInfo, res: &mut SdkResult<HttpResponse>) {
+            self.responses
+                .lock()
+                .unwrap()
+                .push(format!("oauth {} {} {}", req.method, info.attempts, res.is_ok()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware() {
+        let recorder = Arc::new(Recorder::default());
+        let (sdk, transport) = mock_sdk_with(|builder| builder.middleware(recorder.clone()));
+        transport
+            .push_json(&json!({"status": "ok", "msg": "", "data": 3}))
+            .push_json(&json!({"status": "error", "msg": "boom"}))
+            .push_json(&json!({"access_token": "token", "token_type": "bearer"}));
+        assert_eq!(3, sdk.get_user_count(crate::QueryUserSet::All).await.unwrap());
+        let err = sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        assert_eq!(
+            "rewritten: boom [GET /api/get-user-count?owner=built-in&isOnline=]",
+            err.to_string()
+        );
+        let token = sdk.authn().get_oauth_token("code".to_owned()).await.unwrap();
+        assert_eq!("token", token.access_token().secret());
+
+        let requests = transport.requests();
+        assert!(requests.iter().all(|req| req.headers["x-correlation-id"] == "abc"));
+        assert_eq!("http://casdoor.test/api/login/oauth/access_token", requests[2].url);
+        assert_eq!(
+            vec!["GET 1 true", "GET 1 true", "oauth POST 1 true"],
+            *recorder.responses.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credentials() {
+        let (sdk, transport) = mock_sdk();
+        for _ in 0..3 {
+            transport.push_json(&json!({"status": "ok", "msg": "", "data": 1}));
+        }
+        let user_sdk = sdk.with_credentials(ApiCredentials::AccessKey {
+            access_key: "ak".to_owned(),
+            access_secret: "as/+".into(),
+        });
+        user_sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        let req = transport.last_request().unwrap();
+        assert_eq!(
+            "http://casdoor.test/api/get-user-count?owner=built-in&isOnline=&accessKey=ak&accessSecret=as%2F%2B",
+            req.url
+        );
+        assert!(!req.headers.contains_key(AUTHORIZATION));
+        let debug = format!("{req:?} {transport:?}");
+        assert!(debug.contains("accessKey=***&accessSecret=***"), "{debug}");
+        assert!(!debug.contains("as%2F%2B"), "{debug}");
+
+        let token_sdk = user_sdk.with_credentials(ApiCredentials::Bearer { token: "t0k".into() });
+        token_sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        assert_eq!("Bearer t0k", transport.last_request().unwrap().headers[AUTHORIZATION]);
+
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        assert_eq!(ApiCredentials::ClientBasic, sdk.credentials());
+        assert_eq!(
+            "Basic Y2lkOnNlY3JldA==",
+            transport.last_request().unwrap().headers[AUTHORIZATION]
+        );
+
+        let debug = format!("{user_sdk:?} {token_sdk:?}");
+        assert!(debug.contains("client_secret: ***"));
+        assert!(!debug.contains("\"secret\"") && !debug.contains("as/+") && !debug.contains("t0k"));
+
+        transport.push_json(&json!({"status": "error", "msg": "Unauthorized operation"}));
+        let err = user_sdk.get_user_count(crate::QueryUserSet::All).await.unwrap_err();
+        let debug = format!("{err} {err:?}");
+        assert!(debug.contains("accessSecret=***"), "{debug}");
+        assert!(!debug.contains("as%2F%2B") && !debug.contains("as/+"), "{debug}");
+    }
+
+    #[tokio::test]
+    async fn test_org_and_app_handles() {
+        let (sdk, transport) = mock_sdk();
+        transport
+            .push_json(&json!({"status": "ok", "msg": "", "data": 1}))
+            .push_json(&json!({"status": "ok", "msg": "", "data": {"owner": "acme", "name": "alice"}}))
+            .push_json(&json!({"status": "ok", "msg": "", "data": 1}));
+        let acme = sdk.with_org("acme");
+        assert_eq!("acme/alice", acme.id("alice"));
+        assert_eq!("userId=acme%2Falice", acme.user_id_query("alice"));
+
+        acme.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        assert_eq!(
+            "http://casdoor.test/api/get-user-count?owner=acme&isOnline=",
+            transport.last_request().unwrap().url
+        );
+        let user = acme.get_model_by_name::<crate::User>("alice".to_owned()).await.unwrap();
+        assert_eq!("acme", user.unwrap().owner);
+        assert_eq!(
+            "http://casdoor.test/api/get-user?id=acme/alice",
+            transport.last_request().unwrap().url
+        );
+        assert_eq!(
+            "Basic Y2lkOnNlY3JldA==",
+            transport.last_request().unwrap().headers[AUTHORIZATION]
+        );
+
+        let portal = acme.with_app("portal");
+        assert_eq!("acme", portal.org_name());
+        assert_eq!(Some("portal".to_owned()), portal.app_name());
+        assert_eq!(
+            "http://casdoor.test/signup/portal",
+            portal.authn().get_signup_url_enable_password()
+        );
+
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        assert_eq!(
+            "http://casdoor.test/api/get-user-count?owner=built-in&isOnline=",
+            transport.last_request().unwrap().url
+        );
+        assert_eq!(None, sdk.app_name());
+
+        // Only the overridden names stick across a reload.
+        let portal = sdk.with_app("portal");
+        sdk.reload(Config::new(
+            "http://casdoor.test",
+            "cid",
+            "secret",
+            "",
+            "reloaded",
+            Some("shop".to_owned()),
+        ));
+        assert_eq!("acme", acme.org_name());
+        assert_eq!(Some("shop".to_owned()), acme.app_name());
+        assert_eq!("reloaded", portal.org_name());
+        assert_eq!(Some("portal".to_owned()), portal.app_name());
+        assert_eq!(Some("portal".to_owned()), acme.with_app("portal").app_name());
+    }
 
     #[test]
     fn test_res_json() {
         let json_data = r#"{"data":{"accessKey":"test"},"data2":null,"name":"","status":"ok","msg":"test","sub":""}"#;
-        let obj: ApiResponse<HashMap<String, String>, ()> = serde_json::from_str(&json_data).unwrap();
+        let obj: ApiResponse<HashMap<String, String>, ()> = serde_json::from_str(json_data).unwrap();
         println!("{obj:?}");
         let json_data2 = serde_json::to_string(&obj).unwrap();
         assert_eq!(json_data, json_data2);
@@ -138,6 +138,18 @@ pub struct QueryResult<M> {
     total: i64,
 }
 
+impl<M> QueryResult<M> {
+    pub fn items(&self) -> &[M] {
+        &self.items
+    }
+    pub const fn total(&self) -> i64 {
+        self.total
+    }
+    pub fn into_items(self) -> Vec<M> {
+        self.items
+    }
+}
+
 impl<M> From<(Vec<M>, i64)> for QueryResult<M> {
     #[inline(always)]
     fn from(value: (Vec<M>, i64)) -> Self {
@@ -0,0 +1,278 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{Endpoints, FailoverPolicy, Sdk};
+use crate::{Config, ConfigError, ParsedCertificate};
+
+/// The part of a [`Sdk`] that [`Sdk::reload`] swaps.
+#[derive(Debug)]
+pub(crate) struct ConfigState {
+    pub(crate) config: Arc<Config>,
+    pub(crate) endpoints: Arc<Endpoints>,
+    /// The parsed certificate of the config, `None` if it is empty or
+    /// invalid.
+    certificate: Option<Arc<ParsedCertificate>>,
+    /// Certificates replaced by a reload and until when they are still
+    /// accepted.
+    retired_certificates: Vec<(Arc<ParsedCertificate>, Instant)>,
+}
+
+impl ConfigState {
+    pub(crate) fn new(config: Config, failover_policy: FailoverPolicy) -> Self {
+        Self {
+            endpoints: Arc::new(Endpoints::new(&config, failover_policy)),
+            certificate: parse_certificate(&config),
+            config: Arc::new(config),
+            retired_certificates: Vec::new(),
+        }
+    }
+}
+
+fn parse_certificate(config: &Config) -> Option<Arc<ParsedCertificate>> {
+    ParsedCertificate::parse(config.certificate()).ok().map(Arc::new)
+}
+
+impl Sdk {
+    /// Atomically replaces the config of this Sdk and all its clones and
+    /// handles. Requests in flight finish with the old config.
+    ///
+    /// A replaced certificate is still accepted by
+    /// [`AuthSdk::parse_jwt_token`](crate::AuthSdk::parse_jwt_token) for the
+    /// [certificate overlap](super::SdkBuilder::certificate_overlap). The
+    /// health of the endpoints is kept unless they changed.
+    pub fn reload(&self, config: Config) {
+        let mut state = self.inner.write_state();
+        let now = Instant::now();
+        state.retired_certificates.retain(|(_, until)| *until > now);
+        let overlap = self.inner.certificate_overlap;
+        if state.config.certificate() != config.certificate() {
+            let certificate = std::mem::replace(&mut state.certificate, parse_certificate(&config));
+            if let Some(certificate) = certificate.filter(|_| !overlap.is_zero()) {
+                state.retired_certificates.push((certificate, now + overlap));
+            }
+        }
+        if !state.endpoints.urls().iter().eq(config.endpoints()) {
+            state.endpoints = Arc::new(Endpoints::new(&config, self.inner.failover_policy.clone()));
+        }
+        state.config = Arc::new(config);
+    }
+
+    /// Reloads the config from a file, see [`Config::from_file`] and
+    /// [`Sdk::reload`]. The current config is kept if the file fails to load.
+    pub fn reload_from_file(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        self.reload(Config::from_file(path)?);
+        Ok(())
+    }
+
+    /// Reloads the config from the file, see [`Sdk::reload_from_file`],
+    /// whenever its content changes, checking every `interval`.
+    /// A change that fails to load is reported to `on_error` and skipped.
+    /// Never returns; spawn it on the runtime and drop it to stop watching.
+    pub async fn watch_config_file(
+        &self,
+        path: impl Into<PathBuf>,
+        interval: Duration,
+        on_error: impl Fn(ConfigError),
+    ) {
+        let path = path.into();
+        // Compare digests rather than modification times, which may not change
+        // between quick rewrites on filesystems with a coarse resolution.
+        let digest = |path: &Path| std::fs::read(path).ok().map(Sha256::digest);
+        let mut last_digest = digest(&path);
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let current = digest(&path);
+            if current.is_none() || current == last_digest {
+                continue;
+            }
+            last_digest = current;
+            if let Err(e) = self.reload_from_file(&path) {
+                on_error(e);
+            }
+        }
+    }
+
+    /// The certificates JWTs may be signed with: the one of the config, if
+    /// any, followed by those replaced within the certificate overlap.
+    pub(crate) fn verification_certificates(&self) -> Result<Vec<Arc<ParsedCertificate>>, ConfigError> {
+        let state = self.inner.read_state();
+        let current = match &state.certificate {
+            Some(certificate) => Some(certificate.clone()),
+            None if state.config.certificate().is_empty() => None,
+            None => Some(Arc::new(ParsedCertificate::parse(state.config.certificate())?)),
+        };
+        let now = Instant::now();
+        Ok(current
+            .into_iter()
+            .chain(
+                state
+                    .retired_certificates
+                    .iter()
+                    .rev()
+                    .filter(|(_, until)| *until > now)
+                    .map(|(certificate, _)| certificate.clone()),
+            )
+            .collect())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::default_numeric_fallback)]
+mod tests {
+    use jsonwebtoken::{Algorithm, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        MockTransport, StatusCode,
+        header::AUTHORIZATION,
+        test_support::{EC256_CERT, EC256_KEY, RSA1_CERT, RSA1_KEY, RSA2_CERT, RSA2_KEY, claims, sign},
+    };
+
+    fn config(client_secret: &str, certificate: &str) -> Config {
+        Config::new(
+            "http://casdoor.test",
+            "cid",
+            client_secret,
+            certificate,
+            "built-in",
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        let transport = MockTransport::new();
+        let sdk = config("secret", "")
+            .with_fallback_endpoints(["http://b.casdoor.test"])
+            .sdk_builder()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let clone = sdk.clone();
+        transport.push_error(crate::SdkError::new(
+            StatusCode::BAD_GATEWAY,
+            crate::SdkInnerError::TransportError("connection refused".to_owned()),
+        ));
+        transport.push_json(&json!({"status": "ok", "msg": "", "data": 1}));
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+
+        clone.reload(
+            config("rotated", "")
+                .with_fallback_endpoints(["http://b.casdoor.test"])
+                .with_public_endpoint("https://login.example.com"),
+        );
+        assert_eq!("https://login.example.com", sdk.config().browser_endpoint());
+        assert!(!sdk.endpoint_health()[0].healthy);
+        transport.push_json(&json!({"status": "ok", "msg": "", "data": 1}));
+        sdk.get_user_count(crate::QueryUserSet::All).await.unwrap();
+        let req = transport.last_request().unwrap();
+        assert!(req.url.starts_with("http://b.casdoor.test/"));
+        assert_eq!("Basic Y2lkOnJvdGF0ZWQ=", req.headers[AUTHORIZATION]);
+
+        sdk.reload(config("rotated", ""));
+        assert_eq!(1, sdk.endpoint_health().len());
+    }
+
+    #[test]
+    fn test_certificate_overlap() {
+        let claims = claims();
+        let old_token = sign(RSA1_KEY, None, &claims);
+        let new_token = sign(RSA2_KEY, None, &claims);
+
+        let sdk = config("secret", RSA1_CERT)
+            .sdk_builder()
+            .certificate_overlap(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let authn = sdk.authn();
+        assert_eq!("alice", authn.parse_jwt_token(&old_token).unwrap().user.name);
+        assert!(authn.parse_jwt_token(&new_token).is_err());
+        sdk.reload(config("secret", RSA2_CERT));
+        assert_eq!("alice", authn.parse_jwt_token(&old_token).unwrap().user.name);
+        assert_eq!("alice", authn.parse_jwt_token(&new_token).unwrap().user.name);
+
+        let sdk = Sdk::new(config("secret", RSA1_CERT));
+        sdk.reload(config("secret", RSA2_CERT));
+        assert!(sdk.authn().parse_jwt_token(&old_token).is_err());
+        assert!(sdk.authn().parse_jwt_token(&new_token).is_ok());
+
+        let ec_token = jsonwebtoken::encode(
+            &Header::new(Algorithm::ES256),
+            &claims,
+            &EncodingKey::from_ec_pem(EC256_KEY.as_bytes()).unwrap(),
+        )
+        .unwrap();
+        let sdk = config("secret", RSA1_CERT)
+            .sdk_builder()
+            .certificate_overlap(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        sdk.reload(config("secret", EC256_CERT));
+        assert_eq!("alice", sdk.authn().parse_jwt_token(&ec_token).unwrap().user.name);
+        assert_eq!("alice", sdk.authn().parse_jwt_token(&old_token).unwrap().user.name);
+
+        let err = Sdk::new(config("secret", "garbage"))
+            .authn()
+            .parse_jwt_token(&old_token)
+            .unwrap_err();
+        assert!(matches!(
+            err.inner,
+            crate::SdkInnerError::ConfigError(ConfigError::InvalidCertificate(_))
+        ));
+    }
+
+    /// Whether the condition holds within a few seconds.
+    async fn eventually(condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            if Instant::now() > deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        true
+    }
+
+    #[tokio::test]
+    async fn test_watch_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("casdoor.toml");
+        let toml = |org_name: &str| {
+            format!(
+                "endpoint = \"http://casdoor.test\"\nclient_id = \"cid\"\nclient_secret = \"secret\"\norg_name = \
+                 \"{org_name}\"\n"
+            )
+        };
+        std::fs::write(&path, toml("built-in")).unwrap();
+        let sdk = Sdk::new(Config::from_file(&path).unwrap());
+        let errors = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let watcher = {
+            let (sdk, path, errors) = (sdk.clone(), path.clone(), errors.clone());
+            tokio::spawn(async move {
+                sdk.watch_config_file(path, Duration::from_millis(5), |e| {
+                    errors.lock().unwrap().push(e.to_string());
+                })
+                .await;
+            })
+        };
+        // Let the watcher read the file before it changes.
+        tokio::task::yield_now().await;
+        // Same length and, likely, the same modification time.
+        std::fs::write(&path, toml("acme-org")).unwrap();
+        assert!(eventually(|| sdk.org_name() == "acme-org").await);
+
+        std::fs::write(&path, "endpoint = ").unwrap();
+        assert!(eventually(|| errors.lock().unwrap().len() == 1).await);
+        assert_eq!("acme-org", sdk.org_name());
+        watcher.abort();
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use crate::{Method, StatusCode};
+
+/// Controls how failed requests are retried.
+///
+/// Idempotent requests (GET and read-only POSTs such as `enforce`) are retried
+/// on transport errors and on the statuses in `retry_on`, waiting at least the
+/// `Retry-After` of the response. Mutations such as `modify_model` are only
+/// retried if `retry_mutations` is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between two attempts. A response with a
+    /// longer `Retry-After` is not retried.
+    pub max_backoff: Duration,
+    /// Randomize each delay between half and the full backoff.
+    pub jitter: bool,
+    /// HTTP statuses that are worth another attempt.
+    pub retry_on: Vec<StatusCode>,
+    /// Also retry non-idempotent requests.
+    pub retry_mutations: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            jitter: true,
+            retry_on: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_mutations: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries. Idempotent requests still fail over to
+    /// the other endpoints, unless
+    /// [`FailoverPolicy::try_every_endpoint`](crate::FailoverPolicy::try_every_endpoint)
+    /// is `false`.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn max_attempts_for(&self, method: &Method, idempotency: Idempotency) -> u32 {
+        if idempotency.is_idempotent(method) || self.retry_mutations {
+            self.max_attempts.max(1)
+        } else {
+            1
+        }
+    }
+
+    /// The delay after the given failed attempt, starting at 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+        let backoff = self.initial_backoff.saturating_mul(factor).min(self.max_backoff);
+        if !self.jitter {
+            return backoff;
+        }
+        let millis = u64::try_from(backoff.as_millis()).unwrap_or(u64::MAX);
+        Duration::from_millis(fastrand::u64(millis / 2..=millis))
+    }
+
+    /// The delay after the given failed attempt of a response with the
+    /// `Retry-After`, or `None` if it asks to wait longer than `max_backoff`.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_backoff => None,
+            Some(retry_after) => Some(self.backoff(attempt).max(retry_after)),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Whether a request may be sent more than once.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    /// Derived from the method: GET, HEAD and OPTIONS are idempotent.
+    #[default]
+    Auto,
+    /// The request has no side effects, whatever its method.
+    Idempotent,
+    /// The request must not be repeated unless explicitly allowed.
+    NonIdempotent,
+}
+
+impl Idempotency {
+    pub const fn is_idempotent(self, method: &Method) -> bool {
+        match self {
+            Idempotency::Auto => matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS),
+            Idempotency::Idempotent => true,
+            Idempotency::NonIdempotent => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+        assert_eq!(Duration::from_millis(100), policy.backoff(1));
+        assert_eq!(Duration::from_millis(200), policy.backoff(2));
+        assert_eq!(Duration::from_millis(400), policy.backoff(3));
+        assert_eq!(Duration::from_secs(2), policy.backoff(10));
+        assert_eq!(Duration::from_secs(2), policy.backoff(100));
+
+        let policy = RetryPolicy::default();
+        for attempt in 1..5 {
+            let backoff = policy.backoff(attempt);
+            let full = RetryPolicy {
+                jitter: false,
+                ..Default::default()
+            }
+            .backoff(attempt);
+            assert!(backoff >= full / 2 && backoff <= full);
+        }
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+        assert_eq!(Some(Duration::from_millis(100)), policy.delay(1, None));
+        assert_eq!(
+            Some(Duration::from_secs(1)),
+            policy.delay(1, Some(Duration::from_secs(1)))
+        );
+        assert_eq!(Some(Duration::from_millis(400)), policy.delay(3, Some(Duration::ZERO)));
+        assert_eq!(None, policy.delay(1, Some(Duration::from_secs(120))));
+    }
+
+    #[test]
+    fn test_max_attempts() {
+        let policy = RetryPolicy::default();
+        assert_eq!(3, policy.max_attempts_for(&Method::GET, Idempotency::Auto));
+        assert_eq!(1, policy.max_attempts_for(&Method::POST, Idempotency::Auto));
+        assert_eq!(3, policy.max_attempts_for(&Method::POST, Idempotency::Idempotent));
+        assert_eq!(1, policy.max_attempts_for(&Method::GET, Idempotency::NonIdempotent));
+        let policy = RetryPolicy {
+            retry_mutations: true,
+            ..Default::default()
+        };
+        assert_eq!(3, policy.max_attempts_for(&Method::POST, Idempotency::Auto));
+        assert_eq!(1, RetryPolicy::none().max_attempts_for(&Method::GET, Idempotency::Auto));
+    }
+}
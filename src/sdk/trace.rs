@@ -0,0 +1,92 @@
+use std::future::Future;
+
+use super::{HttpRequest, Status};
+use crate::{SdkError, StatusCode};
+
+/// A span covering one logical request, including its retries.
+/// Without the `tracing` feature it does nothing.
+pub(crate) struct RequestTrace {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl RequestTrace {
+    pub(crate) fn new(req: &HttpRequest, operation: &str) -> Self {
+        let path_and_query = req.path_and_query();
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query.as_str(), None),
+        };
+        let span = tracing::debug_span!(
+            "casdoor.request",
+            operation = %operation,
+            method = %req.method,
+            path = %path,
+            query = tracing::field::Empty,
+            http.status = tracing::field::Empty,
+            casdoor.status = tracing::field::Empty,
+            casdoor.msg = tracing::field::Empty,
+            attempts = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+        if let Some(query) = query {
+            span.record("query", crate::utils::redact_query(query));
+        }
+        Self {
+            span,
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub(crate) async fn instrument<F: Future>(&self, fut: F) -> F::Output {
+        tracing::Instrument::instrument(fut, self.span.clone()).await
+    }
+
+    pub(crate) fn record_http(&self, status: StatusCode, attempts: u32) {
+        self.span.record("http.status", status.as_u16());
+        self.span.record("attempts", attempts);
+    }
+
+    /// Records the outcome; `status` is the Casdoor status of API responses.
+    pub(crate) fn finish(&self, result: Result<Option<&Status>, &SdkError>) {
+        let latency_ms = u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.span.record("latency_ms", latency_ms);
+        let _guard = self.span.enter();
+        match result {
+            Ok(status) => {
+                if let Some(status) = status {
+                    let (casdoor_status, msg) = match status {
+                        Status::Ok(msg) => ("ok", msg.as_str()),
+                        Status::Err(msg) => ("error", msg.as_str()),
+                        Status::Other { status, msg } => (status.as_str(), msg.as_str()),
+                    };
+                    self.span.record("casdoor.status", casdoor_status);
+                    self.span.record("casdoor.msg", msg);
+                }
+                tracing::debug!("casdoor request finished");
+            }
+            Err(e) => {
+                self.span.record("attempts", e.attempts());
+                tracing::warn!(error = %e, "casdoor request failed");
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl RequestTrace {
+    pub(crate) const fn new(_req: &HttpRequest, _operation: &str) -> Self {
+        Self {}
+    }
+
+    pub(crate) async fn instrument<F: Future>(&self, fut: F) -> F::Output {
+        fut.await
+    }
+
+    pub(crate) const fn record_http(&self, _status: StatusCode, _attempts: u32) {}
+
+    pub(crate) const fn finish(&self, _result: Result<Option<&Status>, &SdkError>) {}
+}
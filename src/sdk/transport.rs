@@ -0,0 +1,254 @@
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use reqwest::header::{AsHeaderName, CONTENT_TYPE, HeaderMap, HeaderValue, RETRY_AFTER};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Method, SdkError, SdkInnerError, SdkResult, StatusCode};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A fully prepared HTTP request, as produced by the SDK.
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// Redacts the secrets in the query of the URL, see
+/// [`redact_url`](crate::utils::redact_url).
+impl Debug for HttpRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &crate::utils::redact_url(&self.url))
+            .field("headers", &self.headers)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+impl HttpRequest {
+    /// The path and query of the URL, e.g. `/api/get-users?owner=built-in`.
+    pub fn path_and_query(&self) -> String {
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{query}", url.path()),
+                None => url.path().to_owned(),
+            },
+            Err(_) => self.url.clone(),
+        }
+    }
+
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+/// The raw HTTP response returned by a [`Transport`].
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: StatusCode, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Create a response with a JSON body and the matching content type.
+    ///
+    /// # Panics
+    /// If `value` cannot be serialized to JSON.
+    pub fn json(status: StatusCode, value: &impl Serialize) -> Self {
+        let mut res = Self::new(status, serde_json::to_vec(value).expect("Serialize the JSON body"));
+        res.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        res
+    }
+
+    /// The `Retry-After` delay, if given in seconds.
+    pub fn retry_after(&self) -> Option<Duration> {
+        retry_after(&self.headers)
+    }
+
+    /// The start of the body, for logging unexpected responses.
+    pub fn body_snippet(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.body.get(..BODY_SNIPPET_LEN).unwrap_or(&self.body))
+    }
+}
+
+/// Maximum number of body bytes returned by [`HttpResponse::body_snippet`].
+pub const BODY_SNIPPET_LEN: usize = 256;
+
+/// The status and headers of a response, see
+/// [`ApiResponse::http`](crate::ApiResponse::http).
+#[derive(Debug, Clone, Default)]
+pub struct ResponseHead {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+}
+
+impl ResponseHead {
+    /// The header value, if present and visible ASCII.
+    pub fn header(&self, name: impl AsHeaderName) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header(CONTENT_TYPE)
+    }
+
+    /// The `X-Request-Id` set by Casdoor or a proxy in front of it.
+    pub fn request_id(&self) -> Option<&str> {
+        self.header("x-request-id")
+    }
+
+    /// The `Retry-After` delay, if given in seconds.
+    pub fn retry_after(&self) -> Option<Duration> {
+        retry_after(&self.headers)
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// The HTTP layer used by [`Sdk`](crate::Sdk) to talk to Casdoor.
+///
+/// [`ReqwestTransport`] is used by default, [`MockTransport`] serves canned
+/// responses for tests.
+pub trait Transport: Debug + Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, SdkResult<HttpResponse>>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub const fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub const fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, SdkResult<HttpResponse>> {
+        Box::pin(async move {
+            let mut req = self
+                .client
+                .request(request.method, request.url)
+                .headers(request.headers);
+            if !request.body.is_empty() {
+                req = req.body(request.body);
+            }
+            let res = req.send().await?;
+            let status = res.status();
+            let headers = res.headers().clone();
+            let body = res.bytes().await?.to_vec();
+            Ok(HttpResponse { status, headers, body })
+        })
+    }
+}
+
+/// An in-memory transport that records every request and replies with
+/// queued responses in FIFO order.
+///
+/// Clones share the same queue and request log, so keep a clone to inspect
+/// the requests after handing one to the SDK.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: VecDeque<SdkResult<HttpResponse>>,
+    requests: Vec<HttpRequest>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn push_response(&self, response: HttpResponse) -> &Self {
+        self.state().responses.push_back(Ok(response));
+        self
+    }
+
+    /// Queue a `200 OK` response with the JSON body.
+    pub fn push_json(&self, value: &impl Serialize) -> &Self {
+        self.push_response(HttpResponse::json(StatusCode::OK, value))
+    }
+
+    /// Queue a transport failure.
+    pub fn push_error(&self, error: SdkError) -> &Self {
+        self.state().responses.push_back(Err(error));
+        self
+    }
+
+    /// All requests sent so far, oldest first.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.state().requests.clone()
+    }
+
+    pub fn last_request(&self) -> Option<HttpRequest> {
+        self.state().requests.last().cloned()
+    }
+
+    /// Number of queued responses that have not been consumed yet.
+    pub fn pending(&self) -> usize {
+        self.state().responses.len()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, SdkResult<HttpResponse>> {
+        let mut state = self.state();
+        let url = request.url.clone();
+        state.requests.push(request);
+        let res = state.responses.pop_front().unwrap_or_else(|| {
+            Err(SdkError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SdkInnerError::TransportError(format!("MockTransport: no response queued for {url}")),
+            ))
+        });
+        Box::pin(async move { res })
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroize;
+
+/// A credential that is printed as `***` by `Debug` and wiped from memory
+/// when dropped. It serializes as the wrapped value, so the wire format is
+/// unchanged.
+///
+/// There is deliberately no `Display` or `Deref`: use
+/// [`Secret::expose_secret`] where the value is really needed.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Secret<T: Zeroize = String>(T);
+
+impl<T: Zeroize> Secret<T> {
+    pub const fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub const fn expose_secret(&self) -> &T {
+        &self.0
+    }
+}
+
+impl Secret {
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize> Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl<T: Zeroize + Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Zeroize + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(feature = "salvo")]
+impl<T: Zeroize + salvo::oapi::ToSchema> salvo::oapi::ToSchema for Secret<T> {
+    fn to_schema(components: &mut salvo::oapi::Components) -> salvo::oapi::RefOr<salvo::oapi::schema::Schema> {
+        T::to_schema(components)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret() {
+        let secret = Secret::from("s3cr3t");
+        assert_eq!("***", format!("{secret:?}"));
+        assert_eq!("s3cr3t", secret.expose_secret());
+        assert_eq!("\"s3cr3t\"", serde_json::to_string(&secret).unwrap());
+        assert_eq!(secret, serde_json::from_str("\"s3cr3t\"").unwrap());
+
+        let codes = Secret::new(vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!("***", format!("{codes:?}"));
+        assert_eq!("[\"a\",\"b\"]", serde_json::to_string(&codes).unwrap());
+    }
+}
@@ -0,0 +1,76 @@
+//! Fixtures shared by the unit tests.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use p521::{
+    ecdsa::{Signature, SigningKey, signature::Signer},
+    pkcs8::DecodePrivateKey,
+};
+use serde_json::{Value, json};
+
+use crate::{Config, MockTransport, Sdk, SdkBuilder};
+
+pub(crate) const RSA1_KEY: &str = include_str!("../testdata/rsa1.key");
+pub(crate) const RSA1_CERT: &str = include_str!("../testdata/rsa1.crt");
+pub(crate) const RSA2_KEY: &str = include_str!("../testdata/rsa2.key");
+pub(crate) const RSA2_CERT: &str = include_str!("../testdata/rsa2.crt");
+pub(crate) const EC256_KEY: &str = include_str!("../testdata/ec256.key");
+pub(crate) const EC256_CERT: &str = include_str!("../testdata/ec256.crt");
+pub(crate) const EC384_KEY: &str = include_str!("../testdata/ec384.key");
+pub(crate) const EC384_CERT: &str = include_str!("../testdata/ec384.crt");
+pub(crate) const EC521_KEY: &str = include_str!("../testdata/ec521.key");
+pub(crate) const EC521_CERT: &str = include_str!("../testdata/ec521.crt");
+
+/// The config of the test Casdoor, with the certificate.
+pub(crate) fn config(certificate: &str) -> Config {
+    Config::new("http://casdoor.test", "cid", "secret", certificate, "built-in", None)
+}
+
+pub(crate) fn mock_sdk() -> (Sdk, MockTransport) {
+    mock_sdk_with(|builder| builder)
+}
+
+pub(crate) fn mock_sdk_with(f: impl FnOnce(SdkBuilder) -> SdkBuilder) -> (Sdk, MockTransport) {
+    mock_sdk_for("", f)
+}
+
+/// A [`Sdk`] of the [`config`] with the certificate, sending requests to the
+/// returned transport.
+pub(crate) fn mock_sdk_for(certificate: &str, f: impl FnOnce(SdkBuilder) -> SdkBuilder) -> (Sdk, MockTransport) {
+    let transport = MockTransport::new();
+    let sdk = f(config(certificate).sdk_builder())
+        .transport(transport.clone())
+        .build()
+        .unwrap();
+    (sdk, transport)
+}
+
+/// The claims of a valid token of `built-in/alice` for the [`config`].
+pub(crate) fn claims() -> Value {
+    json!({
+        "owner": "built-in",
+        "name": "alice",
+        "aud": ["cid"],
+        "exp": jsonwebtoken::get_current_timestamp() + 3600,
+    })
+}
+
+/// An RS256 token signed with the private key.
+pub(crate) fn sign(key: &str, kid: Option<&str>, claims: &Value) -> String {
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = kid.map(ToOwned::to_owned);
+    jsonwebtoken::encode(&header, claims, &EncodingKey::from_rsa_pem(key.as_bytes()).unwrap()).unwrap()
+}
+
+/// An ES512 token signed with the P-521 private key, which jsonwebtoken
+/// cannot sign.
+pub(crate) fn sign_es512(key: &str, kid: Option<&str>, claims: &Value) -> String {
+    let key = SigningKey::from_bytes(&p521::SecretKey::from_pkcs8_pem(key).unwrap().to_bytes()).unwrap();
+    let message = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(json!({"alg": "ES512", "typ": "JWT", "kid": kid}).to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let signature: Signature = key.sign(message.as_bytes());
+    format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+}
@@ -70,13 +70,9 @@ impl Sdk {
     /// NOTE: oldPassword is not required, if you don't need, just pass a empty
     /// string
     pub async fn set_user_password(&self, mut args: SetPasswordArgs) -> SdkResult<()> {
-        args.user_owner.get_or_insert(self.org_name().to_owned());
-        self.request_data(
-            Method::POST,
-            "/api/set-password",
-            Body::Form(&serde_urlencoded::to_string(args)?),
-        )
-        .await?
-        .into_data_default()
+        args.user_owner.get_or_insert_with(|| self.org_name());
+        self.request_data(Method::POST, "/api/set-password", Body::Form(&args))
+            .await?
+            .into_data_default()
     }
 }
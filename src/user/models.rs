@@ -2,7 +2,7 @@ use std::{collections::HashMap, fmt::Display};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{IsQueryArgs, Model, Permission, Role, utils::null_to_default};
+use crate::{IsQueryArgs, Model, Permission, Role, Secret, utils::null_to_default};
 
 #[cfg_attr(feature = "salvo", derive(salvo::prelude::ToSchema))]
 #[derive(Serialize, Deserialize, Debug)]
@@ -45,7 +45,7 @@ pub struct User {
     pub id: String,
     pub external_id: String,
     pub r#type: String,
-    pub password: String,
+    pub password: Secret,
     pub password_salt: String,
     pub password_type: String,
     pub display_name: String,
@@ -87,8 +87,8 @@ pub struct User {
     pub hash: String,
     pub pre_hash: String,
     pub access_key: String,
-    pub access_secret: String,
-    pub access_token: String,
+    pub access_secret: Secret,
+    pub access_token: Secret,
     pub created_ip: String,
     pub last_signin_time: String,
     pub last_signin_ip: String,
@@ -173,8 +173,8 @@ pub struct User {
     pub webauthn_credentials: Vec<String>,
     pub preferred_mfa_type: String,
     #[serde(deserialize_with = "null_to_default")]
-    pub recovery_codes: Vec<String>,
-    pub totp_secret: String,
+    pub recovery_codes: Secret<Vec<String>>,
+    pub totp_secret: Secret,
     pub mfa_phone_enabled: bool,
     pub mfa_email_enabled: bool,
     #[serde(deserialize_with = "null_to_default")]
@@ -435,6 +435,7 @@ pub struct SetPasswordArgs {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::default_numeric_fallback)]
 mod tests {
     use super::*;
     #[test]
@@ -526,4 +527,30 @@ mod tests {
         let casdoor_user: User = serde_json::from_str(json_data).expect("JSON parsing failed");
         println!("{:?}", casdoor_user);
     }
+
+    #[test]
+    fn test_user_secrets() {
+        let json_data = r#"
+    {
+        "owner": "built-in",
+        "name": "alice",
+        "password": "example_password",
+        "accessSecret": "example_access_secret",
+        "accessToken": "example_access_token",
+        "totpSecret": "example_totp_secret",
+        "recoveryCodes": null
+    }
+    "#;
+
+        let casdoor_user: User = serde_json::from_str(json_data).expect("JSON parsing failed");
+        assert_eq!("example_password", casdoor_user.password.expose_secret());
+        assert!(casdoor_user.recovery_codes.expose_secret().is_empty());
+        let debug = format!("{casdoor_user:?}");
+        assert!(!debug.contains("example_password"));
+        assert!(!debug.contains("example_access_secret"));
+        assert!(!debug.contains("example_totp_secret"));
+        assert!(!debug.contains("example_access_token"));
+        let value = serde_json::to_value(&casdoor_user).unwrap();
+        assert_eq!("example_password", value["password"]);
+    }
 }
@@ -7,3 +7,75 @@ where
 {
     Ok(Option::deserialize(deserializer)?.unwrap_or_default())
 }
+
+/// Query parameters whose values must not appear in logs.
+pub const SENSITIVE_QUERY_KEYS: &[&str] = &[
+    "id",
+    "userId",
+    "username",
+    "name",
+    "email",
+    "phone",
+    "countryCode",
+    "password",
+    "oldPassword",
+    "newPassword",
+    "accessKey",
+    "accessSecret",
+    "accessToken",
+    "access_token",
+    "clientSecret",
+    "client_secret",
+    "code",
+    "token",
+    "refresh_token",
+    "sessionId",
+    "sessionPkId",
+];
+
+/// Replaces the values of [`SENSITIVE_QUERY_KEYS`] in the query part of the
+/// URL with `***`.
+pub fn redact_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((path, query)) => format!("{path}?{}", redact_query(query)),
+        None => url.to_owned(),
+    }
+}
+
+/// Replaces the values of [`SENSITIVE_QUERY_KEYS`] in the query with `***`.
+pub fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SENSITIVE_QUERY_KEYS.contains(&key) => format!("{key}=***"),
+            _ => pair.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_url() {
+        assert_eq!(
+            "/api/get-users?owner=built-in",
+            redact_url("/api/get-users?owner=built-in")
+        );
+        assert_eq!("/api/get-users", redact_url("/api/get-users"));
+        assert_eq!(
+            "http://localhost:8000/api/get-user?owner=built-in&email=***&x&phone=***",
+            redact_url("http://localhost:8000/api/get-user?owner=built-in&email=a@b.c&x&phone=123")
+        );
+        assert_eq!(
+            "/api/get-user?userId=***&",
+            redact_url("/api/get-user?userId=built-in%2Falice&")
+        );
+        assert_eq!(
+            "id=***&owner=built-in&username=***&countryCode=***&phone=***",
+            redact_query("id=built-in/alice&owner=built-in&username=alice&countryCode=US&phone=123")
+        );
+    }
+}